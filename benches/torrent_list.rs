@@ -0,0 +1,42 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use qbit_rs::model::{Torrent, TorrentSummary};
+
+/// A `torrents/info` response with `n` torrents, with the fields reported by
+/// qBittorrent
+fn torrent_list(n: usize) -> String {
+    let torrents = (0..n)
+        .map(|i| {
+            format!(
+                r#"{{"added_on":{added},"amount_left":0,"auto_tmm":false,"availability":-1,
+"category":"linux","completed":4700000000,"completion_on":{added},
+"content_path":"/downloads/torrent-{i}","dl_limit":-1,"dlspeed":0,"downloaded":4700000000,
+"downloaded_session":0,"eta":8640000,"f_l_piece_prio":false,"force_start":false,
+"hash":"{i:040x}","last_activity":{added},"magnet_uri":"magnet:?xt=urn:btih:{i:040x}",
+"max_ratio":-1,"max_seeding_time":-1,"name":"torrent-{i}","num_complete":10,
+"num_incomplete":2,"num_leechs":0,"num_seeds":0,"priority":0,"progress":1.0,"ratio":1.5,
+"ratio_limit":-2,"save_path":"/downloads","seeding_time":3600,"seeding_time_limit":-2,
+"seen_complete":{added},"seq_dl":false,"size":4700000000,"state":"stalledUP",
+"super_seeding":false,"tags":"iso, seed","time_active":7200,"total_size":4700000000,
+"tracker":"https://tracker.example.org/announce","up_limit":-1,"uploaded":7050000000,
+"uploaded_session":0,"upspeed":0}}"#,
+                added = 1_700_000_000 + i,
+            )
+        })
+        .collect::<Vec<_>>();
+    format!("[{}]", torrents.join(","))
+}
+
+fn deserialize(c: &mut Criterion) {
+    let json = torrent_list(5000);
+    let mut group = c.benchmark_group("torrent_list");
+    group.bench_function("full", |b| {
+        b.iter(|| serde_json::from_str::<Vec<Torrent>>(black_box(&json)).unwrap())
+    });
+    group.bench_function("minimal", |b| {
+        b.iter(|| serde_json::from_str::<Vec<TorrentSummary>>(black_box(&json)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, deserialize);
+criterion_main!(benches);
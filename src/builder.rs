@@ -1,17 +1,57 @@
 #![allow(private_interfaces, private_bounds)]
 
-use std::{fmt::Debug, sync::Mutex};
+use std::{
+    fmt::Debug,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use reqwest::Client;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Client,
+};
 use tap::Pipe;
+use tokio::sync::{OnceCell, Semaphore};
 use url::Url;
 
-use crate::{ext::Cookie, model::Credential, LoginState, Qbit};
+use crate::{
+    cache::MetadataCache, ext::Cookie, model::Credential, transport::Transport, Error, LoginState,
+    Qbit, Result,
+};
+
+const DEFAULT_API_BASE: &str = "api/v2/";
+const DEFAULT_MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
 
 pub struct QbitBuilder<C = (), R = (), E = ()> {
     credential: C,
     client: R,
     endpoint: E,
+    options: Options,
+}
+
+/// Options that don't affect the type of the builder
+#[derive(Default)]
+struct Options {
+    max_concurrent_requests: Option<usize>,
+    proxies: Vec<reqwest::Proxy>,
+    headers: HeaderMap,
+    cache_ttl: Option<Duration>,
+    rate_limit_retries: u32,
+    max_retry_after: Option<Duration>,
+    api_base: Option<String>,
+    transport: Option<Arc<dyn Transport>>,
+    http_version: HttpVersion,
+}
+
+/// HTTP version used by the client created by the builder
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum HttpVersion {
+    /// HTTP/1.1, or HTTP/2 if negotiated with ALPN
+    #[default]
+    Any,
+    Http1Only,
+    Http2PriorKnowledge,
 }
 
 trait IntoLoginState {
@@ -24,6 +64,49 @@ impl IntoLoginState for Cookie {
     }
 }
 
+/// Endpoint given as `host:port`, see [`QbitBuilder::host_port`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostPort(String);
+
+impl From<SocketAddr> for HostPort {
+    fn from(addr: SocketAddr) -> Self {
+        Self(addr.to_string())
+    }
+}
+
+impl From<String> for HostPort {
+    fn from(host_port: String) -> Self {
+        Self(host_port)
+    }
+}
+
+impl From<&str> for HostPort {
+    fn from(host_port: &str) -> Self {
+        Self(host_port.to_owned())
+    }
+}
+
+impl TryFrom<HostPort> for Url {
+    type Error = url::ParseError;
+
+    fn try_from(HostPort(host_port): HostPort) -> Result<Self, Self::Error> {
+        if host_port.contains("://") {
+            Url::parse(&host_port)
+        } else {
+            Url::parse(&format!("http://{host_port}"))
+        }
+    }
+}
+
+/// Marker for a client without authentication, see [`QbitBuilder::no_auth`]
+pub struct NoAuth;
+
+impl IntoLoginState for NoAuth {
+    fn into_login_state(self) -> LoginState {
+        LoginState::NoAuth
+    }
+}
+
 impl IntoLoginState for Credential {
     fn into_login_state(self) -> LoginState {
         LoginState::NotLoggedIn { credential: self }
@@ -36,6 +119,7 @@ impl QbitBuilder {
             credential: (),
             client: (),
             endpoint: (),
+            options: Options::default(),
         }
     }
 }
@@ -52,6 +136,7 @@ impl<C, R, E> QbitBuilder<C, R, E> {
             credential: self.credential,
             client,
             endpoint: self.endpoint,
+            options: self.options,
         }
     }
 
@@ -61,6 +146,7 @@ impl<C, R, E> QbitBuilder<C, R, E> {
             credential: Cookie(cookie.into()),
             client: self.client,
             endpoint: self.endpoint,
+            options: self.options,
         }
     }
 
@@ -69,6 +155,24 @@ impl<C, R, E> QbitBuilder<C, R, E> {
             credential,
             client: self.client,
             endpoint: self.endpoint,
+            options: self.options,
+        }
+    }
+
+    /// Never log in nor send a cookie, for qBittorrent behind a proxy that
+    /// authenticates requests itself, or with authentication bypassed (e.g.
+    /// `bypass_local_auth` or `bypass_auth_subnet_whitelist`).
+    ///
+    /// Anyone who can reach the endpoint can then control qBittorrent, so make
+    /// sure it's only reachable by trusted clients. Rejected requests return
+    /// [`ApiError::NotLoggedIn`](crate::ApiError::NotLoggedIn).
+    #[allow(private_interfaces)]
+    pub fn no_auth(self) -> QbitBuilder<NoAuth, R, E> {
+        QbitBuilder {
+            credential: NoAuth,
+            client: self.client,
+            endpoint: self.endpoint,
+            options: self.options,
         }
     }
 
@@ -80,7 +184,145 @@ impl<C, R, E> QbitBuilder<C, R, E> {
             credential: self.credential,
             client: self.client,
             endpoint,
+            options: self.options,
+        }
+    }
+
+    /// Set the endpoint from a bare `host:port` such as `192.168.1.10:8080` or
+    /// a [`SocketAddr`], using `http`. HTTPS must be explicit, e.g.
+    /// `https://nas.local:8080`, which is used as is.
+    pub fn host_port(self, host_port: impl Into<HostPort>) -> QbitBuilder<C, R, HostPort> {
+        self.endpoint(host_port.into())
+    }
+
+    /// Resend requests rejected with `429 Too Many Requests` by a proxy in
+    /// front of qBittorrent, up to `retries` times. Each retry waits for the
+    /// delay in the `Retry-After` header, or 1s doubling with every retry if
+    /// there's none, up to [`QbitBuilder::max_retry_after`]. Disabled by
+    /// default, see [`Qbit#retries`].
+    pub fn rate_limit_retries(mut self, retries: u32) -> Self {
+        self.options.rate_limit_retries = retries;
+        self
+    }
+
+    /// Longest delay to wait before resending a rate limited request, see
+    /// [`QbitBuilder::rate_limit_retries`]. When a `Retry-After` header asks
+    /// for longer, [`ApiError::RateLimited`](crate::ApiError::RateLimited) is
+    /// returned right away. 60s by default.
+    pub fn max_retry_after(mut self, max: Duration) -> Self {
+        self.options.max_retry_after = Some(max);
+        self
+    }
+
+    /// Send requests with `transport` instead of the HTTP client, e.g. a
+    /// [`MockTransport`](crate::MockTransport) in tests. The client is then
+    /// only used to build requests.
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.options.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Path of the Web API relative to the endpoint, `api/v2/` by default. A
+    /// trailing `/` is added if missing. Checked when building the client,
+    /// see [`QbitBuilder::try_build`].
+    pub fn api_base(mut self, api_base: impl Into<String>) -> Self {
+        let mut api_base = api_base.into();
+        if !api_base.ends_with('/') {
+            api_base.push('/');
         }
+        self.options.api_base = Some(api_base);
+        self
+    }
+
+    /// Limit the number of requests sent to qBittorrent simultaneously.
+    /// Requests over the limit wait until a previous one has received its
+    /// response. Unbounded by default, or when `max` is `0`.
+    pub fn max_concurrent_requests(mut self, max: usize) -> Self {
+        self.options.max_concurrent_requests = (max > 0).then_some(max);
+        self
+    }
+
+    /// Cache the results of [`Qbit::get_all_tags`] and [`Qbit::get_categories`]
+    /// for `ttl`. The cache is shared by clones of the client, and cleared by
+    /// every request that may change tags or categories, such as
+    /// [`Qbit::create_tags`] or [`Qbit::add_category`]. Changes made by other
+    /// clients are only seen once the cached results expire. Disabled by
+    /// default.
+    pub fn cache_metadata(mut self, ttl: Duration) -> Self {
+        self.options.cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Send `X-Forwarded-For: <ip>` with every request, to present `ip` as the
+    /// client address when qBittorrent is reached through a reverse proxy.
+    ///
+    /// qBittorrent only honors the header when reverse proxy support is
+    /// enabled and the request comes from one of its trusted proxies
+    /// (`web_ui_reverse_proxies_list`). The forwarded address is then the one
+    /// checked against `bypass_auth_subnet_whitelist`, and against localhost
+    /// for `bypass_local_auth`, so requests may be accepted without logging
+    /// in.
+    pub fn forwarded_for(mut self, ip: IpAddr) -> Self {
+        self.options.headers.insert(
+            HeaderName::from_static("x-forwarded-for"),
+            HeaderValue::from_str(&ip.to_string()).expect("IP address should be a valid header"),
+        );
+        self
+    }
+}
+
+impl<C, E> QbitBuilder<C, (), E> {
+    /// Route requests through a proxy. Can be called multiple times to add
+    /// several proxies, which are tried in order.
+    ///
+    /// By default the client already honors the standard proxy environment
+    /// variables (`HTTP_PROXY`, `HTTPS_PROXY`, `ALL_PROXY` and `NO_PROXY`).
+    /// Adding a proxy here disables them. Only applies to the client created
+    /// by the builder, a client set later with [`QbitBuilder::client`]
+    /// discards the proxies.
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> Self {
+        self.options.proxies.push(proxy);
+        self
+    }
+
+    /// Only use HTTP/1.1. Disabled by default. Like
+    /// [`QbitBuilder::proxy`], a client set later with [`QbitBuilder::client`]
+    /// ignores it. Overrides [`QbitBuilder::http2_prior_knowledge`].
+    pub fn http1_only(mut self, enabled: bool) -> Self {
+        self.set_http_version(HttpVersion::Http1Only, enabled);
+        self
+    }
+
+    /// Only use HTTP/2, without negotiating it first, e.g. for a reverse proxy
+    /// that only accepts HTTP/2. Disabled by default. Like
+    /// [`QbitBuilder::proxy`], a client set later with [`QbitBuilder::client`]
+    /// ignores it. Overrides [`QbitBuilder::http1_only`].
+    pub fn http2_prior_knowledge(mut self, enabled: bool) -> Self {
+        self.set_http_version(HttpVersion::Http2PriorKnowledge, enabled);
+        self
+    }
+
+    fn set_http_version(&mut self, version: HttpVersion, enabled: bool) {
+        if enabled {
+            self.options.http_version = version;
+        } else if self.options.http_version == version {
+            self.options.http_version = HttpVersion::Any;
+        }
+    }
+
+    fn client_builder(&mut self) -> reqwest::ClientBuilder {
+        let builder = std::mem::take(&mut self.options.proxies)
+            .into_iter()
+            .fold(Client::builder(), |builder, proxy| builder.proxy(proxy));
+        match self.options.http_version {
+            HttpVersion::Any => builder,
+            HttpVersion::Http1Only => builder.http1_only(),
+            HttpVersion::Http2PriorKnowledge => builder.http2_prior_knowledge(),
+        }
+    }
+
+    fn build_client(&mut self) -> reqwest::Result<Client> {
+        self.client_builder().build()
     }
 }
 
@@ -91,14 +333,58 @@ where
     U::Error: Debug,
 {
     pub fn build(self) -> Qbit {
-        let endpoint = self.endpoint.try_into().expect("Invalid endpoint");
+        self.try_build().expect("Invalid endpoint")
+    }
+
+    /// Same as [`QbitBuilder::build`], but returns [`Error::InvalidEndpoint`]
+    /// instead of panicking when the endpoint, or the endpoint joined with
+    /// [`QbitBuilder::api_base`], is not a valid URL.
+    pub fn try_build(self) -> Result<Qbit> {
+        let endpoint: Url = self
+            .endpoint
+            .try_into()
+            .map_err(|e| Error::InvalidEndpoint(format!("{e:?}")))?;
+        let api_base = endpoint
+            .join(self.options.api_base.as_deref().unwrap_or(DEFAULT_API_BASE))
+            .map_err(|e| Error::InvalidEndpoint(format!("{e:?}")))?;
         let state = self.credential.into_login_state().pipe(Mutex::new);
 
-        Qbit {
+        Ok(Qbit {
+            transport: self
+                .options
+                .transport
+                .unwrap_or_else(|| Arc::new(self.client.clone())),
             client: self.client,
             endpoint,
             state,
-        }
+            login_lock: tokio::sync::Mutex::new(()),
+            limiter: self
+                .options
+                .max_concurrent_requests
+                .map(|max| Arc::new(Semaphore::new(max))),
+            headers: self.options.headers,
+            cache: self
+                .options
+                .cache_ttl
+                .map(|ttl| Arc::new(MetadataCache::new(ttl))),
+            default_save_path: Mutex::new(None),
+            webapi_version: OnceCell::new(),
+            rate_limit_retries: self.options.rate_limit_retries,
+            max_retry_after: self
+                .options
+                .max_retry_after
+                .unwrap_or(DEFAULT_MAX_RETRY_AFTER),
+            api_base,
+        })
+    }
+
+    /// Build the client and request `app/version` to check that the cookie or
+    /// credential is accepted, e.g. to fail fast at startup. A rejected cookie
+    /// returns [`ApiError::NotLoggedIn`](crate::ApiError::NotLoggedIn).
+    pub async fn build_and_verify(self) -> Result<Qbit> {
+        let api = self.try_build()?;
+        api.get_version().await?;
+        Ok(api)
     }
 }
 
@@ -108,8 +394,23 @@ where
     U: TryInto<Url>,
     U::Error: Debug,
 {
-    pub fn build(self) -> Qbit {
-        self.client(reqwest::Client::new()).build()
+    pub fn build(mut self) -> Qbit {
+        let client = self.build_client().expect("Failed to build HTTP client");
+        self.client(client).build()
+    }
+
+    /// Same as [`QbitBuilder::build`], but returns [`Error::InvalidEndpoint`]
+    /// when the endpoint is not a valid URL and [`Error::HttpError`] when the
+    /// HTTP client can't be built, instead of panicking.
+    pub fn try_build(mut self) -> Result<Qbit> {
+        let client = self.build_client()?;
+        self.client(client).try_build()
+    }
+
+    /// Same as [`QbitBuilder::build_and_verify`] with a default client.
+    pub async fn build_and_verify(mut self) -> Result<Qbit> {
+        let client = self.build_client()?;
+        self.client(client).build_and_verify().await
     }
 }
 
@@ -137,3 +438,82 @@ fn test_builder() {
         .cookie("SID=1234567890")
         .build();
 }
+
+#[test]
+fn test_max_concurrent_requests() {
+    let limiter = |max| {
+        QbitBuilder::new()
+            .endpoint("http://localhost:8080")
+            .cookie("SID=1234567890")
+            .max_concurrent_requests(max)
+            .build()
+            .limiter
+            .map(|limiter| limiter.available_permits())
+    };
+
+    assert_eq!(limiter(2), Some(2));
+    assert_eq!(limiter(0), None);
+}
+
+#[test]
+fn test_host_port() {
+    let endpoint = |host_port: HostPort| {
+        QbitBuilder::new()
+            .host_port(host_port)
+            .cookie("SID=1234567890")
+            .build()
+            .endpoint
+            .to_string()
+    };
+
+    assert_eq!(
+        endpoint("192.168.1.10:8080".into()),
+        "http://192.168.1.10:8080/"
+    );
+    assert_eq!(
+        endpoint(SocketAddr::from(([127, 0, 0, 1], 8080)).into()),
+        "http://127.0.0.1:8080/"
+    );
+    assert_eq!(endpoint("nas.local:8080".into()), "http://nas.local:8080/");
+    assert_eq!(
+        endpoint("https://nas.local:8080".into()),
+        "https://nas.local:8080/"
+    );
+}
+
+#[tokio::test]
+async fn test_http_version() {
+    use reqwest::Version;
+
+    // Accepts both HTTP/1.1 and HTTP/2 without negotiation
+    let server = wiremock::MockServer::start().await;
+    let version = |mut builder: QbitBuilder| {
+        let client = builder.build_client().unwrap();
+        let uri = server.uri();
+        async move { client.get(uri).send().await.unwrap().version() }
+    };
+
+    assert_eq!(version(QbitBuilder::new()).await, Version::HTTP_11);
+    assert_eq!(
+        version(QbitBuilder::new().http2_prior_knowledge(true)).await,
+        Version::HTTP_2
+    );
+    assert_eq!(
+        version(
+            QbitBuilder::new()
+                .http2_prior_knowledge(true)
+                .http1_only(true)
+        )
+        .await,
+        Version::HTTP_11
+    );
+    assert_eq!(
+        version(
+            QbitBuilder::new()
+                .http2_prior_knowledge(true)
+                .http2_prior_knowledge(false)
+        )
+        .await,
+        Version::HTTP_11
+    );
+}
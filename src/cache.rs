@@ -0,0 +1,106 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::model::Category;
+
+/// Endpoints that may change tags or categories, clearing the cache
+pub(crate) const METADATA_ENDPOINTS: &[&str] = &[
+    "torrents/add",
+    "torrents/addTags",
+    "torrents/createTags",
+    "torrents/deleteTags",
+    "torrents/createCategory",
+    "torrents/editCategory",
+    "torrents/removeCategories",
+];
+
+/// Cache of tags and categories, see
+/// [`QbitBuilder::cache_metadata`](crate::QbitBuilder::cache_metadata)
+pub(crate) struct MetadataCache {
+    pub(crate) tags: Entry<Vec<String>>,
+    pub(crate) categories: Entry<HashMap<String, Category>>,
+}
+
+impl MetadataCache {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            tags: Entry::new(ttl),
+            categories: Entry::new(ttl),
+        }
+    }
+
+    pub(crate) fn clear(&self) {
+        self.tags.clear();
+        self.categories.clear();
+    }
+}
+
+pub(crate) struct Entry<T> {
+    ttl: Duration,
+    value: Mutex<Slot<T>>,
+}
+
+struct Slot<T> {
+    /// Incremented by every clear
+    generation: u64,
+    value: Option<(Instant, T)>,
+}
+
+impl<T: Clone> Entry<T> {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            value: Mutex::new(Slot {
+                generation: 0,
+                value: None,
+            }),
+        }
+    }
+
+    /// Cached value, if it has not expired yet
+    pub(crate) fn get(&self) -> Option<T> {
+        match &self.value.lock().unwrap().value {
+            Some((at, value)) if at.elapsed() < self.ttl => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    /// Generation to pass to [`Entry::set`], read before fetching the value
+    pub(crate) fn generation(&self) -> u64 {
+        self.value.lock().unwrap().generation
+    }
+
+    /// Cache a value fetched when the entry was at `generation`. It's dropped
+    /// if the entry was cleared meanwhile, since it may predate the change.
+    pub(crate) fn set(&self, generation: u64, value: T) {
+        let mut slot = self.value.lock().unwrap();
+        if slot.generation == generation {
+            slot.value = Some((Instant::now(), value));
+        }
+    }
+
+    fn clear(&self) {
+        let mut slot = self.value.lock().unwrap();
+        slot.generation += 1;
+        slot.value = None;
+    }
+}
+
+#[test]
+fn test_entry_cleared_while_fetching() {
+    let entry = Entry::new(Duration::from_secs(60));
+    entry.set(entry.generation(), 1);
+    assert_eq!(entry.get(), Some(1));
+
+    // Fetch started before a change that cleared the cache
+    let generation = entry.generation();
+    entry.clear();
+    entry.set(generation, 2);
+    assert_eq!(entry.get(), None);
+
+    entry.set(entry.generation(), 3);
+    assert_eq!(entry.get(), Some(3));
+}
@@ -1,28 +1,55 @@
-use reqwest::{header::SET_COOKIE, Response, StatusCode};
+//! Helpers to handle raw [`Response`]s, e.g. the ones returned by
+//! [`Qbit::request_raw`](crate::Qbit::request_raw).
+
+use std::future::Future;
+
+use reqwest::{
+    header::{CONTENT_TYPE, SET_COOKIE},
+    Response, ResponseBuilderExt, StatusCode,
+};
 use tap::Pipe;
 
 use crate::{ApiError, Error, Result};
 
+/// Types that can be extracted from a response, see [`ResponseExt::extract`]
 pub trait FromResponse {
     fn from_response(response: &Response) -> Result<Self>
     where
         Self: Sized;
 }
 
-pub struct Cookie(pub String);
+/// Session cookie as `name=value`, ready to be sent in the `Cookie` header
+pub(crate) struct Cookie(pub(crate) String);
+
+impl Cookie {
+    /// Whether the cookie name is the one used by qBittorrent for sessions.
+    /// qBittorrent 5 appends the WebUI port, e.g. `QBT_SID_8080`.
+    fn is_session(name: &str) -> bool {
+        name == "SID" || name.starts_with("QBT_SID_")
+    }
+}
 
 impl FromResponse for Cookie {
     fn from_response(response: &Response) -> Result<Self> {
-        let cookie = response
-            .headers()
-            .get(SET_COOKIE)
+        let mut pairs = Vec::new();
+        for value in response.headers().get_all(SET_COOKIE) {
+            let value = value.to_str().map_err(|_| Error::NonAsciiHeader)?;
+            // Only the leading `name=value` pair is sent back, attributes like
+            // `HttpOnly` or `path` are not part of the cookie
+            let pair = value.split(';').next().unwrap_or_default().trim();
+            if let Some((name, _)) = pair.split_once('=') {
+                pairs.push((name, pair));
+            }
+        }
+
+        pairs
+            .iter()
+            .find(|(name, _)| Self::is_session(name))
+            .or(pairs.first())
+            .map(|(_, pair)| Self(pair.to_string()))
             .ok_or(Error::BadResponse {
                 explain: "Failed to extract cookie from response",
-            })?
-            .to_str()
-            .map_err(|_| Error::NonAsciiHeader)?
-            .to_owned();
-        Ok(Self(cookie))
+            })
     }
 }
 
@@ -32,12 +59,36 @@ impl FromResponse for () {
     }
 }
 
+/// Message of a JSON error body, stored in the response extensions by
+/// [`ResponseExt::read_error_message`]
+#[derive(Clone)]
+struct ErrorMessage(String);
+
+/// Extension trait for [`Response`], used by all built-in methods to turn
+/// status codes into [`Error`]s.
 pub trait ResponseExt: Sized {
+    /// Extract `T` from the response, without checking its status
     fn extract<T: FromResponse>(&self) -> Result<T>;
 
+    /// Map a non-success status to an error with `f`, e.g. one of
+    /// [`TORRENT_NOT_FOUND`] or [`SEARCH_NOT_FOUND`]. Statuses that `f` maps to
+    /// `None` are passed through, except `403 Forbidden`, which becomes
+    /// [`ApiError::NotLoggedIn`].
     fn map_status<F: FnOnce(StatusCode) -> Option<Error>>(self, f: F) -> Result<Self>;
 
+    /// Fail with [`Error::UnknownHttpCode`] on any non-success status, then
+    /// extract `T`. If [`ResponseExt::read_error_message`] found a message,
+    /// [`Error::ServerError`] is returned instead.
     fn end<T: FromResponse>(self) -> Result<T>;
+
+    /// On a non-success status with a JSON body like `{"error": "..."}`, read
+    /// the message so that [`ResponseExt::end`] can report it. Other responses
+    /// are returned untouched. Built-in methods already do this for every
+    /// response.
+    fn read_error_message(self) -> impl Future<Output = Result<Self>> + Send;
+
+    /// Message found by [`ResponseExt::read_error_message`], if any
+    fn error_message(&self) -> Option<&str>;
 }
 
 impl ResponseExt for Response {
@@ -62,8 +113,58 @@ impl ResponseExt for Response {
     }
 
     fn end<T: FromResponse>(self) -> Result<T> {
-        self.map_status(|c| Error::UnknownHttpCode(c).pipe(Some))
-            .and_then(|b| T::from_response(&b).map_err(Into::into))
+        let message = self.error_message().map(str::to_owned);
+        self.map_status(|status| {
+            match message {
+                Some(message) => Error::ServerError { status, message },
+                None => Error::UnknownHttpCode(status),
+            }
+            .pipe(Some)
+        })
+        .and_then(|b| T::from_response(&b))
+    }
+
+    async fn read_error_message(self) -> Result<Self> {
+        #[derive(serde::Deserialize)]
+        struct ErrorBody {
+            error: String,
+        }
+
+        let is_json = self
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("application/json"));
+        if self.status().is_success() || !is_json {
+            return Ok(self);
+        }
+
+        // The body can only be read once, so the response is rebuilt from it
+        let mut builder = http::Response::builder()
+            .status(self.status())
+            .version(self.version())
+            .url(self.url().clone());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = self.headers().clone();
+        }
+        let body = self.bytes().await?;
+        let message = serde_json::from_slice::<ErrorBody>(&body).ok();
+        let mut res = builder
+            .body(body)
+            .map(Response::from)
+            .map_err(|_| Error::BadResponse {
+                explain: "Failed to rebuild error response",
+            })?;
+        if let Some(ErrorBody { error }) = message {
+            res.extensions_mut().insert(ErrorMessage(error));
+        }
+        Ok(res)
+    }
+
+    fn error_message(&self) -> Option<&str> {
+        self.extensions()
+            .get::<ErrorMessage>()
+            .map(|m| m.0.as_str())
     }
 }
 
@@ -75,3 +176,89 @@ pub const TORRENT_NOT_FOUND: fn(StatusCode) -> Option<Error> = |s| {
         None
     }
 };
+
+/// Handle 404 returned by APIs with search job id as a parameter
+pub const SEARCH_NOT_FOUND: fn(StatusCode) -> Option<Error> = |s| {
+    if s == StatusCode::NOT_FOUND {
+        Some(Error::ApiError(ApiError::SearchJobNotFound))
+    } else {
+        None
+    }
+};
+
+#[test]
+fn test_cookie_from_response() {
+    let response = |set_cookies: &[&str]| {
+        let mut builder = http::Response::builder();
+        for set_cookie in set_cookies {
+            builder = builder.header(SET_COOKIE, *set_cookie);
+        }
+        Response::from(builder.body("").unwrap())
+    };
+
+    let Cookie(cookie) = response(&["SID=abc; HttpOnly; path=/; SameSite=Strict"])
+        .extract()
+        .unwrap();
+    assert_eq!(cookie, "SID=abc");
+
+    let Cookie(cookie) = response(&["other=1; path=/", "QBT_SID_8080=abc; HttpOnly"])
+        .extract()
+        .unwrap();
+    assert_eq!(cookie, "QBT_SID_8080=abc");
+
+    assert!(response(&[]).extract::<Cookie>().is_err());
+}
+
+#[tokio::test]
+async fn test_read_error_message() {
+    let response = |content_type: &str, body: &'static str| {
+        http::Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .header(CONTENT_TYPE, content_type)
+            .body(body)
+            .unwrap()
+            .pipe(Response::from)
+    };
+
+    let res = response("application/json", r#"{"error":"Invalid hash"}"#)
+        .read_error_message()
+        .await
+        .unwrap();
+    assert_eq!(res.error_message(), Some("Invalid hash"));
+    assert!(matches!(
+        res.end::<()>(),
+        Err(Error::ServerError { status: StatusCode::BAD_REQUEST, message }) if message == "Invalid hash"
+    ));
+
+    // Falls back to the status when the body isn't the expected shape
+    for (content_type, body) in [
+        ("application/json", r#"{"other":1}"#),
+        ("text/plain", r#"{"error":"Invalid hash"}"#),
+    ] {
+        let res = response(content_type, body)
+            .read_error_message()
+            .await
+            .unwrap();
+        assert_eq!(res.error_message(), None);
+        assert!(matches!(
+            res.end::<()>(),
+            Err(Error::UnknownHttpCode(StatusCode::BAD_REQUEST))
+        ));
+    }
+}
+
+#[test]
+fn test_cookie_non_ascii() {
+    let value = http::HeaderValue::from_bytes(b"SID=\xe4\xbd\xa0; HttpOnly").unwrap();
+    let response = Response::from(
+        http::Response::builder()
+            .header(SET_COOKIE, value)
+            .body("")
+            .unwrap(),
+    );
+
+    assert!(matches!(
+        response.extract::<Cookie>(),
+        Err(Error::NonAsciiHeader)
+    ));
+}
@@ -4,26 +4,34 @@
 
 use std::{
     borrow::Borrow,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt::Debug,
     path::{Path, PathBuf},
-    sync::{Mutex, MutexGuard},
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
 };
 
 pub mod model;
-pub use builder::QbitBuilder;
+pub use builder::{HostPort, QbitBuilder};
 use bytes::Bytes;
-use reqwest::{header, Client, Method, Response, StatusCode};
+pub use ext::{ResponseExt, SEARCH_NOT_FOUND, TORRENT_NOT_FOUND};
+use reqwest::{header, Client, Method, RequestBuilder, Response, ResponseBuilderExt, StatusCode};
 use serde::Serialize;
 use serde_with::skip_serializing_none;
-use tap::{Pipe, TapFallible};
+use tap::Pipe;
+use tokio::sync::{OnceCell, Semaphore, SemaphorePermit};
 use tracing::{debug, trace, warn};
+#[cfg(feature = "mock")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "mock")))]
+pub use transport::MockTransport;
 use url::Url;
 
-use crate::{ext::*, model::*};
+use crate::{cache::*, ext::*, model::*, transport::Transport};
 
 mod builder;
+mod cache;
 mod ext;
+pub mod transport;
 
 #[derive(Clone)]
 enum LoginState {
@@ -37,28 +45,58 @@ enum LoginState {
         cookie: String,
         credential: Credential,
     },
+    /// Authentication is handled outside of qBittorrent, see
+    /// [`QbitBuilder::no_auth`]
+    NoAuth,
 }
 
 impl LoginState {
     fn as_cookie(&self) -> Option<&str> {
         match self {
             Self::CookieProvided { cookie } => Some(cookie),
-            Self::NotLoggedIn { .. } => None,
+            Self::NotLoggedIn { .. } | Self::NoAuth => None,
             Self::LoggedIn { cookie, .. } => Some(cookie),
         }
     }
 
     fn as_credential(&self) -> Option<&Credential> {
         match self {
-            Self::CookieProvided { .. } => None,
+            Self::CookieProvided { .. } | Self::NoAuth => None,
             Self::NotLoggedIn { credential } => Some(credential),
             Self::LoggedIn { credential, .. } => Some(credential),
         }
     }
 
+    /// Name of the state without the credential or cookie, used by the `Debug`
+    /// implementation of [`Qbit`]
+    fn name(&self) -> &'static str {
+        match self {
+            Self::CookieProvided { .. } | Self::LoggedIn { .. } => "Authenticated",
+            Self::NotLoggedIn { .. } => "NotAuthenticated",
+            Self::NoAuth => "AuthDisabled",
+        }
+    }
+
+    /// Forget `cookie` after logging out, unless it was already replaced.
+    /// A cookie given to [`QbitBuilder::cookie`] is kept, since it can't be
+    /// obtained again.
+    fn remove_cookie(&mut self, cookie: &str) {
+        if let Self::LoggedIn {
+            cookie: current,
+            credential,
+        } = self
+        {
+            if current == cookie {
+                *self = Self::NotLoggedIn {
+                    credential: credential.clone(),
+                };
+            }
+        }
+    }
+
     fn add_cookie(&mut self, cookie: String) {
         match self {
-            Self::CookieProvided { .. } => {}
+            Self::CookieProvided { .. } | Self::NoAuth => {}
             Self::LoggedIn { credential, .. } | Self::NotLoggedIn { credential } => {
                 *self = Self::LoggedIn {
                     cookie,
@@ -69,15 +107,84 @@ impl LoginState {
     }
 }
 
+/// Outcome of [`Qbit::login_with_outcome`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginOutcome {
+    /// A login request was sent and a new cookie was obtained
+    Authenticated,
+    /// A cookie is already present, no login request was sent
+    AlreadyLoggedIn,
+    /// Authentication is disabled with [`QbitBuilder::no_auth`], no login
+    /// request was sent
+    AuthDisabled,
+}
+
 /// Main entry point of the library. It provides a high-level API to interact
 /// with qBittorrent WebUI API.
+///
+/// # Retries
+///
+/// qBittorrent checks the session before processing a request and rejects an
+/// expired or invalid cookie with a bare `403 Forbidden`. When that happens the
+/// client logs in again and resends the request, up to 3 attempts in total.
+/// Concurrent requests share a single login instead of each sending one.
+///
+/// Requests whose effect adds up when applied twice (adding torrents, toggles,
+/// moving queue positions, starting a search, ...) are never resent. For
+/// those the client still logs in again, then returns
+/// [`ApiError::NotLoggedIn`] and leaves it up to the caller to retry.
+///
+/// A `429 Too Many Requests` from a proxy in front of qBittorrent returns
+/// [`ApiError::RateLimited`], unless retries are enabled with
+/// [`QbitBuilder::rate_limit_retries`] and the requested delay is at most
+/// [`QbitBuilder::max_retry_after`].
 pub struct Qbit {
+    /// Used to build requests, which are sent with `transport`
     client: Client,
+    transport: Arc<dyn Transport>,
     endpoint: Url,
+    /// Only locked briefly and never across an await, so that it can be read
+    /// from sync code such as `Clone`
     state: Mutex<LoginState>,
+    /// Held while logging in, so that concurrent requests without a valid
+    /// cookie wait for a single login instead of each sending one
+    login_lock: tokio::sync::Mutex<()>,
+    limiter: Option<Arc<Semaphore>>,
+    /// Headers sent with every request
+    headers: header::HeaderMap,
+    cache: Option<Arc<MetadataCache>>,
+    /// See [`Qbit::default_save_path_cached`]
+    default_save_path: Mutex<Option<PathBuf>>,
+    /// Web API version, used to check for endpoints added in newer versions
+    webapi_version: OnceCell<String>,
+    /// See [`QbitBuilder::rate_limit_retries`]
+    rate_limit_retries: u32,
+    /// See [`QbitBuilder::max_retry_after`]
+    max_retry_after: Duration,
+    /// Endpoint joined with [`QbitBuilder::api_base`]
+    api_base: Url,
+}
+
+/// Only prints the endpoint and whether a session is established; the
+/// credential and cookie are never printed.
+impl Debug for Qbit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Qbit")
+            .field("endpoint", &self.endpoint.as_str())
+            .field("api_base", &self.api_base.as_str())
+            .field("auth", &format_args!("{}", self.state().name()))
+            .finish_non_exhaustive()
+    }
 }
 
 impl Qbit {
+    /// How often [`Qbit::add_torrent_selective`] checks whether the metadata
+    /// of a torrent was downloaded
+    pub const METADATA_POLL_INTERVAL: Duration = Duration::from_millis(500);
+    /// How long [`Qbit::add_torrent_selective`] waits for the metadata of a
+    /// torrent before giving up
+    pub const METADATA_TIMEOUT: Duration = Duration::from_secs(300);
+
     /// Create a new [`QbitBuilder`] to build a [`Qbit`] instance.
     pub fn builder() -> QbitBuilder {
         QbitBuilder::new()
@@ -103,6 +210,35 @@ impl Qbit {
         Self::new_with_client(endpoint, credential, Client::new())
     }
 
+    /// Same as [`Qbit::new_with_client`], but returns
+    /// [`Error::InvalidEndpoint`] instead of panicking when the endpoint is not
+    /// a valid URL.
+    pub fn try_new_with_client<U>(
+        endpoint: U,
+        credential: Credential,
+        client: Client,
+    ) -> Result<Self>
+    where
+        U: TryInto<Url>,
+        U::Error: Debug,
+    {
+        Self::builder()
+            .endpoint(endpoint)
+            .credential(credential)
+            .client(client)
+            .try_build()
+    }
+
+    /// Same as [`Qbit::new`], but returns [`Error::InvalidEndpoint`] instead
+    /// of panicking when the endpoint is not a valid URL.
+    pub fn try_new<U>(endpoint: U, credential: Credential) -> Result<Self>
+    where
+        U: TryInto<Url>,
+        U::Error: Debug,
+    {
+        Self::try_new_with_client(endpoint, credential, Client::new())
+    }
+
     #[deprecated = "Use `QbitBuilder::cookie` instead"]
     pub fn with_cookie(self, cookie: impl Into<String>) -> Self {
         Self {
@@ -121,8 +257,34 @@ impl Qbit {
             .map(ToOwned::to_owned)
     }
 
+    /// End the session. The cookie is forgotten, so the next request logs in
+    /// again. Requests sent with the cookie meanwhile are rejected, and log in
+    /// again like for an expired session, see [`Qbit#retries`].
     pub async fn logout(&self) -> Result<()> {
-        self.get("auth/logout").await?.end()
+        let cookie = self.get_cookie().await;
+        self.get("auth/logout").await?.end::<()>()?;
+        if let Some(cookie) = cookie {
+            self.state().remove_cookie(&cookie);
+        }
+        Ok(())
+    }
+
+    /// Log out and consume the client, so that short-lived programs don't
+    /// leave sessions behind on the server. Nothing is sent without a session,
+    /// i.e. before logging in or with [`QbitBuilder::no_auth`].
+    ///
+    /// Dropping a client never logs out, since that can't be awaited.
+    ///
+    /// Clones of a client share its session, so closing one logs all of them
+    /// out. The others are then rejected on their next request and log in
+    /// again with their credential, or fail with [`ApiError::NotLoggedIn`] if
+    /// they were given a cookie. Only close the last clone in use.
+    pub async fn close(self) -> Result<()> {
+        if self.state().as_cookie().is_none() {
+            return Ok(());
+        }
+
+        self.logout().await
     }
 
     pub async fn get_version(&self) -> Result<String> {
@@ -130,6 +292,7 @@ impl Qbit {
             .await?
             .text()
             .await
+            .map(|version| version.trim().to_owned())
             .map_err(Into::into)
     }
 
@@ -138,6 +301,7 @@ impl Qbit {
             .await?
             .text()
             .await
+            .map(|version| version.trim().to_owned())
             .map_err(Into::into)
     }
 
@@ -149,8 +313,18 @@ impl Qbit {
             .map_err(Into::into)
     }
 
+    /// Shut down qBittorrent. The server may go away before it finishes
+    /// replying, so a connection dropped in the middle of the exchange is
+    /// treated as a successful shutdown. Failing to connect at all, or any
+    /// error status returned by the server, is still reported as an error.
     pub async fn shutdown(&self) -> Result<()> {
-        self.post("app/shutdown", NONE).await?.end()
+        match self.post("app/shutdown", NONE).await {
+            Err(Error::HttpError(e)) if is_connection_dropped(&e) => {
+                debug!(error = ?e, "Connection dropped after shutdown, assuming success");
+                Ok(())
+            }
+            res => res?.end(),
+        }
     }
 
     pub async fn get_preferences(&self) -> Result<Preferences> {
@@ -165,6 +339,65 @@ impl Qbit {
         &self,
         preferences: impl Borrow<Preferences> + Send + Sync,
     ) -> Result<()> {
+        self.post_preferences(preferences.borrow()).await
+    }
+
+    /// Same as [`Qbit::set_preferences`], but can also clear preferences, see
+    /// [`PreferencesPatch`].
+    pub async fn set_preferences_patch(
+        &self,
+        patch: impl Borrow<PreferencesPatch> + Send + Sync,
+    ) -> Result<()> {
+        self.post_preferences(patch.borrow()).await
+    }
+
+    /// Set the port for incoming connections, leaving other preferences
+    /// unchanged. Port `0` returns [`Error::InvalidPort`] without sending the
+    /// request.
+    pub async fn set_listen_port(&self, port: u16) -> Result<()> {
+        if port == 0 {
+            return Err(Error::InvalidPort(port));
+        }
+
+        self.set_preferences(Preferences {
+            listen_port: Some(port.into()),
+            ..Default::default()
+        })
+        .await
+    }
+
+    /// Whether qBittorrent resolves the country of peers, i.e. the
+    /// `resolve_peer_countries` preference. When disabled, [`Peer::country`]
+    /// and [`Peer::country_code`] are always `None`.
+    pub async fn peer_countries_enabled(&self) -> Result<bool> {
+        self.get_preferences()
+            .await?
+            .resolve_peer_countries
+            .unwrap_or(false)
+            .pipe(Ok)
+    }
+
+    /// Same as [`Qbit::get_torrent_peers`], but first enables the
+    /// `resolve_peer_countries` preference if it's disabled so that the
+    /// countries of peers are filled in. The preference stays enabled
+    /// afterwards, for all torrents.
+    pub async fn get_torrent_peers_with_geo(
+        &self,
+        hash: impl AsRef<str> + Send + Sync,
+        rid: impl Into<Option<i64>> + Send + Sync,
+    ) -> Result<PeerSyncData> {
+        if !self.peer_countries_enabled().await? {
+            self.set_preferences(Preferences {
+                resolve_peer_countries: Some(true),
+                ..Default::default()
+            })
+            .await?;
+        }
+
+        self.get_torrent_peers(hash, rid).await
+    }
+
+    async fn post_preferences(&self, preferences: &(impl Serialize + Sync)) -> Result<()> {
         #[derive(Serialize)]
         struct Arg {
             json: String,
@@ -173,7 +406,7 @@ impl Qbit {
         self.post(
             "app/setPreferences",
             Some(&Arg {
-                json: serde_json::to_string(preferences.borrow())?,
+                json: serde_json::to_string(preferences)?,
             }),
         )
         .await?
@@ -189,14 +422,50 @@ impl Qbit {
             .map(PathBuf::from)
     }
 
+    /// Same as [`Qbit::get_default_save_path`], but only requested once. The
+    /// path is cached until [`Qbit::invalidate_default_save_path`] is called,
+    /// so call it after changing `save_path` in the preferences.
+    pub async fn default_save_path_cached(&self) -> Result<PathBuf> {
+        if let Some(path) = &*self.default_save_path.lock().unwrap() {
+            return Ok(path.clone());
+        }
+
+        let path = self.get_default_save_path().await?;
+        *self.default_save_path.lock().unwrap() = Some(path.clone());
+        Ok(path)
+    }
+
+    /// Forget the path cached by [`Qbit::default_save_path_cached`]
+    pub fn invalidate_default_save_path(&self) {
+        self.default_save_path.lock().unwrap().take();
+    }
+
+    /// Get logs, see [`GetLogsArg::last_known_id`] for how it's normalized.
     pub async fn get_logs(&self, arg: impl Borrow<GetLogsArg> + Send + Sync) -> Result<Vec<Log>> {
-        self.get_with("log/main", arg.borrow())
+        let arg = GetLogsArg {
+            last_known_id: normalize_last_known_id(arg.borrow().last_known_id),
+            ..arg.borrow().clone()
+        };
+
+        self.get_with("log/main", &arg)
             .await?
             .json()
             .await
             .map_err(Into::into)
     }
 
+    /// Get logs of all levels after `last_known_id`, see
+    /// [`GetLogsArg::all_levels`].
+    pub async fn get_recent_logs(
+        &self,
+        last_known_id: impl Into<Option<i64>> + Send + Sync,
+    ) -> Result<Vec<Log>> {
+        self.get_logs(GetLogsArg::all_levels(last_known_id)).await
+    }
+
+    /// Get peer logs with an id greater than `last_known_id`. `-1` or `None`
+    /// gets all of them, and the id is normalized like
+    /// [`GetLogsArg::last_known_id`].
     pub async fn get_peer_logs(
         &self,
         last_known_id: impl Into<Option<i64>> + Send + Sync,
@@ -210,7 +479,7 @@ impl Qbit {
         self.get_with(
             "log/peers",
             &Arg {
-                last_known_id: last_known_id.into(),
+                last_known_id: normalize_last_known_id(last_known_id.into()),
             },
         )
         .await?
@@ -266,6 +535,39 @@ impl Qbit {
             .map_err(Into::into)
     }
 
+    /// Get the transfer info, speed limits mode, global limits and number of
+    /// torrents at once, see [`DashboardSnapshot`].
+    ///
+    /// The five requests are sent at the same time, unless they're limited
+    /// with [`QbitBuilder::max_concurrent_requests`]. The torrent list is
+    /// downloaded to count the torrents, but not parsed.
+    pub async fn get_dashboard_snapshot(&self) -> Result<DashboardSnapshot> {
+        let (transfer, alt_speed_limits, download_limit, upload_limit, torrent_count) = tokio::join!(
+            self.get_transfer_info(),
+            self.get_speed_limits_mode(),
+            self.get_download_limit(),
+            self.get_upload_limit(),
+            self.count_torrents(),
+        );
+
+        Ok(DashboardSnapshot {
+            transfer: transfer?,
+            alt_speed_limits: alt_speed_limits?,
+            download_limit: download_limit?,
+            upload_limit: upload_limit?,
+            torrent_count: torrent_count?,
+        })
+    }
+
+    async fn count_torrents(&self) -> Result<usize> {
+        self.get("torrents/info")
+            .await?
+            .json::<Vec<serde::de::IgnoredAny>>()
+            .await
+            .map(|torrents| torrents.len())
+            .map_err(Into::into)
+    }
+
     pub async fn get_speed_limits_mode(&self) -> Result<bool> {
         self.get("transfer/speedLimitsMode")
             .await?
@@ -281,6 +583,8 @@ impl Qbit {
             })
     }
 
+    /// Toggle alternative speed limits. Never resent automatically, see
+    /// [`Qbit#retries`].
     pub async fn toggle_speed_limits_mode(&self) -> Result<()> {
         self.post("transfer/toggleSpeedLimitsMode", None::<&()>).await?.end()
     }
@@ -350,6 +654,103 @@ impl Qbit {
     }
 
     pub async fn get_torrent_list(&self, arg: GetTorrentListArg) -> Result<Vec<Torrent>> {
+        self.check_torrent_list_arg(&arg).await?;
+
+        self.get_with("torrents/info", &arg)
+            .await?
+            .json()
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Get the `page`-th page (starting from 0) of `page_size` torrents of the
+    /// torrent list. `limit` and `offset` of `arg` are overwritten.
+    ///
+    /// [`TorrentPage::total`] is the number of torrents matching the filters
+    /// of `arg`. It's known for free when the page is the last one, i.e. not
+    /// full. Otherwise it's only counted with `with_total`: qBittorrent can't
+    /// count filtered torrents, so that takes a second, unpaginated request,
+    /// whose entries are counted without being deserialized.
+    pub async fn get_torrent_page(
+        &self,
+        arg: GetTorrentListArg,
+        page: u64,
+        page_size: u64,
+        with_total: bool,
+    ) -> Result<TorrentPage> {
+        let offset = page.saturating_mul(page_size);
+        let count_arg = GetTorrentListArg {
+            sort: None,
+            reverse: None,
+            limit: None,
+            offset: None,
+            include_trackers: None,
+            ..arg.clone()
+        };
+        let items = self
+            .get_torrent_list(GetTorrentListArg {
+                limit: Some(page_size),
+                offset: Some(offset.try_into().unwrap_or(i64::MAX)),
+                ..arg
+            })
+            .await?;
+
+        // An empty page past the end doesn't tell how many torrents there are
+        let last_page = (items.len() as u64) < page_size && (page == 0 || !items.is_empty());
+        let total = if last_page {
+            Some(offset + items.len() as u64)
+        } else if with_total {
+            self.get_with("torrents/info", &count_arg)
+                .await?
+                .json::<Vec<serde::de::IgnoredAny>>()
+                .await
+                .map(|all| Some(all.len() as u64))?
+        } else {
+            None
+        };
+
+        Ok(TorrentPage { items, total })
+    }
+
+    /// Get the list entry, properties, trackers and contents of a torrent in
+    /// one call, see [`TorrentDetail`]. The parts are requested in parallel,
+    /// which counts towards [`QbitBuilder::max_concurrent_requests`]. Returns
+    /// [`ApiError::TorrentNotFound`] if the torrent is not in the list.
+    pub async fn get_torrent_detail(
+        &self,
+        hash: impl AsRef<str> + Send + Sync,
+    ) -> Result<TorrentDetail> {
+        let hash = hash.as_ref();
+        let (list, properties, trackers, contents) = tokio::join!(
+            self.get_torrent_list(GetTorrentListArg {
+                hashes: Some(hash.to_owned()),
+                ..Default::default()
+            }),
+            self.get_torrent_properties(hash),
+            self.get_torrent_trackers(hash),
+            self.get_torrent_contents(hash, None),
+        );
+
+        Ok(TorrentDetail {
+            torrent: list?
+                .into_iter()
+                .next()
+                .ok_or(Error::ApiError(ApiError::TorrentNotFound))?,
+            properties: found(properties)?,
+            trackers: found(trackers)?,
+            contents: found(contents)?,
+        })
+    }
+
+    /// Get the torrent list like [`Qbit::get_torrent_list`], but only keep the
+    /// fields of [`TorrentSummary`]. qBittorrent still sends every field, this
+    /// only saves the allocations for the others.
+    pub async fn get_torrent_list_minimal(
+        &self,
+        arg: GetTorrentListArg,
+    ) -> Result<Vec<TorrentSummary>> {
+        self.check_torrent_list_arg(&arg).await?;
+
         self.get_with("torrents/info", &arg)
             .await?
             .json()
@@ -357,7 +758,86 @@ impl Qbit {
             .map_err(Into::into)
     }
 
+    /// Get torrents in any of `categories` that have any of `tags`, see
+    /// [`filter_torrents`]. The `category` and `tag` of `base` are ignored,
+    /// since the server only filters by a single value of each.
+    ///
+    /// This fetches the full list matching the rest of `base` and filters it
+    /// client-side, so `limit` and `offset` apply before filtering.
+    pub async fn get_torrents_filtered(
+        &self,
+        categories: &[String],
+        tags: &[String],
+        base: GetTorrentListArg,
+    ) -> Result<Vec<Torrent>> {
+        self.get_torrent_list(GetTorrentListArg {
+            category: None,
+            tag: None,
+            ..base
+        })
+        .await
+        .map(|torrents| filter_torrents(torrents, categories, tags))
+    }
+
+    /// Fetch all torrents and group them by category, see
+    /// [`group_torrents_by_category`].
+    pub async fn torrents_grouped_by_category(&self) -> Result<HashMap<String, Vec<Torrent>>> {
+        self.get_torrent_list(GetTorrentListArg::default())
+            .await
+            .map(group_torrents_by_category)
+    }
+
+    /// Fetch all torrents and group them by tag, see [`group_torrents_by_tag`].
+    pub async fn torrents_grouped_by_tag(&self) -> Result<HashMap<String, Vec<Torrent>>> {
+        self.get_torrent_list(GetTorrentListArg::default())
+            .await
+            .map(group_torrents_by_tag)
+    }
+
+    /// Page through the torrent list, `page_size` torrents at a time, starting
+    /// at `base.offset`. Pages are fetched lazily until a short or empty page
+    /// is returned; empty pages are not yielded.
+    ///
+    /// Every page is a new query against live data, so torrents added or
+    /// removed in between may shift across pages, which can cause a torrent to
+    /// be skipped or yielded twice. Set a `sort` on `base` to keep the order
+    /// stable otherwise.
+    #[cfg(feature = "stream")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "stream")))]
+    pub fn torrent_pages(
+        &self,
+        base: GetTorrentListArg,
+        page_size: u64,
+    ) -> impl futures_util::Stream<Item = Result<Vec<Torrent>>> + Send + '_ {
+        let start = base.offset.unwrap_or(0);
+
+        futures_util::stream::unfold(Some(start), move |offset| {
+            let arg = GetTorrentListArg {
+                limit: Some(page_size),
+                offset,
+                ..base.clone()
+            };
+            async move {
+                let offset = offset?;
+                match self.get_torrent_list(arg).await {
+                    Ok(page) if page.is_empty() => None,
+                    Ok(page) => {
+                        let next =
+                            (page.len() as u64 >= page_size).then(|| offset + page.len() as i64);
+                        Some((Ok(page), next))
+                    }
+                    Err(e) => Some((Err(e), None)),
+                }
+            }
+        })
+    }
+
+    /// Export a torrent as a .torrent file. Requires qBittorrent ≥ v4.5.0,
+    /// older versions return [`ApiError::EndpointUnavailable`].
     pub async fn export_torrent(&self, hash: impl AsRef<str> + Send + Sync) -> Result<Bytes> {
+        self.require_webapi_version("torrents/export", "2.8.14", "4.5.0")
+            .await?;
+
         self.get_with("torrents/export", &HashArg::new(hash.as_ref()))
             .await?
             .bytes()
@@ -389,6 +869,38 @@ impl Qbit {
             .map_err(Into::into)
     }
 
+    /// Trackers of a torrent that qBittorrent announces to, in order, according
+    /// to the `announce_to_all_tiers` and `announce_to_all_trackers`
+    /// preferences. See [`announce_order`].
+    pub async fn effective_announce_order(
+        &self,
+        hash: impl AsRef<str> + Send + Sync,
+    ) -> Result<Vec<Tracker>> {
+        let (trackers, preferences) =
+            tokio::join!(self.get_torrent_trackers(hash), self.get_preferences());
+        let (trackers, preferences) = (trackers?, preferences?);
+
+        announce_order(
+            &trackers,
+            preferences.announce_to_all_tiers.unwrap_or(true),
+            preferences.announce_to_all_trackers.unwrap_or(false),
+        )
+        .into_iter()
+        .cloned()
+        .collect::<Vec<_>>()
+        .pipe(Ok)
+    }
+
+    /// Count the trackers of a torrent by status, see [`TrackerHealth`].
+    pub async fn tracker_health(
+        &self,
+        hash: impl AsRef<str> + Send + Sync,
+    ) -> Result<TrackerHealth> {
+        self.get_torrent_trackers(hash)
+            .await
+            .map(|trackers| TrackerHealth::from_trackers(&trackers))
+    }
+
     pub async fn get_torrent_web_seeds(
         &self,
         hash: impl AsRef<str> + Send + Sync,
@@ -401,6 +913,11 @@ impl Qbit {
             .map_err(Into::into)
     }
 
+    /// Get the files of a torrent, optionally only those with the given
+    /// indexes. The [`TorrentContent::index`] of each file is the id expected
+    /// by [`Qbit::set_file_priority`], which is not necessarily its position
+    /// in the returned list. For servers older than API v2.8.2 that don't
+    /// report the index, it's filled in from the position of the file.
     pub async fn get_torrent_contents(
         &self,
         hash: impl AsRef<str> + Send + Sync,
@@ -422,11 +939,31 @@ impl Qbit {
         )
         .await
         .and_then(|r| r.map_status(TORRENT_NOT_FOUND))?
-        .json()
-        .await
+        .json::<Vec<serde_json::Value>>()
+        .await?
+        .into_iter()
+        .enumerate()
+        .map(|(position, mut content)| {
+            if let Some(content) = content.as_object_mut() {
+                content.entry("index").or_insert(position.into());
+            }
+            serde_json::from_value(content)
+        })
+        .collect::<Result<_, _>>()
         .map_err(Into::into)
     }
 
+    /// Availability of a whole torrent computed from its contents, see
+    /// [`content_availability`].
+    pub async fn torrent_content_availability(
+        &self,
+        hash: impl AsRef<str> + Send + Sync,
+    ) -> Result<f64> {
+        self.get_torrent_contents(hash, None)
+            .await
+            .map(|contents| content_availability(&contents))
+    }
+
     pub async fn get_torrent_pieces_states(
         &self,
         hash: impl AsRef<str> + Send + Sync,
@@ -451,18 +988,44 @@ impl Qbit {
             .map_err(Into::into)
     }
 
-    pub async fn pause_torrents(&self, hashes: impl Into<Hashes> + Send + Sync) -> Result<()> {
-        self.post("torrents/pause", Some(&HashesArg::new(hashes)))
+    /// Same as [`Qbit::get_torrent_pieces_hashes`], but decodes each hash into
+    /// its 20 bytes (SHA-1), see [`decode_piece_hash`]. A hash that isn't 40
+    /// hex characters returns [`Error::BadResponse`].
+    pub async fn get_torrent_piece_hashes_bytes(
+        &self,
+        hash: impl AsRef<str> + Send + Sync,
+    ) -> Result<Vec<[u8; 20]>> {
+        self.get_torrent_pieces_hashes(hash)
             .await?
-            .end()
+            .iter()
+            .map(|hash| {
+                decode_piece_hash(hash).ok_or(Error::BadResponse {
+                    explain: "Piece hash is not 40 hex characters",
+                })
+            })
+            .collect()
+    }
+
+    pub async fn pause_torrents(&self, hashes: impl Into<Hashes> + Send + Sync) -> Result<()> {
+        self.post(
+            "torrents/pause",
+            Some(&HashesArg::new(non_empty_hashes(hashes)?)),
+        )
+        .await?
+        .end()
     }
 
     pub async fn resume_torrents(&self, hashes: impl Into<Hashes> + Send + Sync) -> Result<()> {
-        self.post("torrents/resume", Some(&HashesArg::new(hashes)))
-            .await?
-            .end()
+        self.post(
+            "torrents/resume",
+            Some(&HashesArg::new(non_empty_hashes(hashes)?)),
+        )
+        .await?
+        .end()
     }
 
+    /// Delete torrents, optionally with their downloaded data. Never resent
+    /// automatically, see [`Qbit#retries`].
     pub async fn delete_torrents(
         &self,
         hashes: impl Into<Hashes> + Send + Sync,
@@ -478,7 +1041,7 @@ impl Qbit {
         self.post(
             "torrents/delete",
             Some(&Arg {
-                hashes: hashes.into(),
+                hashes: non_empty_hashes(hashes)?,
                 delete_files: delete_files.into(),
             }),
         )
@@ -486,35 +1049,248 @@ impl Qbit {
         .end()
     }
 
+    /// Same as [`Qbit::delete_torrents`], but returns the hashes of the
+    /// requested torrents that existed before and are gone after the deletion.
+    /// Unknown hashes, which qBittorrent silently ignores, are not reported.
+    pub async fn delete_torrents_reporting(
+        &self,
+        hashes: impl Into<Hashes> + Send + Sync,
+        delete_files: impl Into<Option<bool>> + Send + Sync,
+    ) -> Result<Vec<String>> {
+        let requested = self.resolve_hashes(hashes).await?;
+        let existing = self.existing_hashes(&requested).await?;
+        if existing.is_empty() {
+            return Ok(existing);
+        }
+
+        self.delete_torrents(existing.clone(), delete_files).await?;
+
+        let remaining = self.existing_hashes(&existing).await?;
+        Ok(existing
+            .into_iter()
+            .filter(|hash| !remaining.contains(hash))
+            .collect())
+    }
+
     pub async fn recheck_torrents(&self, hashes: impl Into<Hashes> + Send + Sync) -> Result<()> {
-        self.post("torrents/recheck", Some(&HashesArg::new(hashes)))
-            .await?
-            .end()
+        self.post(
+            "torrents/recheck",
+            Some(&HashesArg::new(non_empty_hashes(hashes)?)),
+        )
+        .await?
+        .end()
     }
 
     pub async fn reannounce_torrents(&self, hashes: impl Into<Hashes> + Send + Sync) -> Result<()> {
-        self.post("torrents/reannounce", Some(&HashesArg::new(hashes)))
-            .await?
-            .end()
+        self.post(
+            "torrents/reannounce",
+            Some(&HashesArg::new(non_empty_hashes(hashes)?)),
+        )
+        .await?
+        .end()
+    }
+
+    /// Add torrents like [`Qbit::add_torrent`] and report whether anything was
+    /// added, since qBittorrent silently ignores torrents that are already
+    /// present.
+    ///
+    /// When every source is a magnet link with a v1 info hash (see
+    /// [`magnet_info_hash`]), the hashes are checked against the torrent list
+    /// before adding. Otherwise the full torrent list is fetched before and
+    /// after adding and compared, which is best-effort: torrents added by
+    /// someone else in between are reported as added, and torrents that
+    /// qBittorrent has yet to download from a URL are not.
+    pub async fn add_torrent_checked(
+        &self,
+        arg: impl Borrow<AddTorrentArg> + Send + Sync,
+    ) -> Result<AddOutcome> {
+        let hashes = match &arg.borrow().source {
+            TorrentSource::Urls { urls } => urls
+                .as_slice()
+                .iter()
+                .map(magnet_info_hash)
+                .collect::<Option<Vec<_>>>()
+                .filter(|hashes| !hashes.is_empty()),
+            TorrentSource::TorrentFiles { .. } => None,
+        };
+
+        let added = match hashes {
+            Some(mut hashes) => {
+                hashes.sort();
+                hashes.dedup();
+                let present = self.existing_hashes(&hashes).await?;
+                self.add_torrent(arg).await?;
+                hashes.retain(|hash| !present.contains(hash));
+                hashes
+            }
+            None => {
+                let before = self
+                    .resolve_hashes(Hashes::All)
+                    .await?
+                    .into_iter()
+                    .collect::<HashSet<_>>();
+                self.add_torrent(arg).await?;
+                self.resolve_hashes(Hashes::All)
+                    .await?
+                    .into_iter()
+                    .filter(|hash| !before.contains(hash))
+                    .collect()
+            }
+        };
+        Ok(AddOutcome::from_added(added))
+    }
+
+    /// Add torrents like [`Qbit::add_torrent_checked`], but only download the
+    /// files with the given indexes, see [`TorrentContent::index`]. `None`
+    /// downloads every file.
+    ///
+    /// The torrents are added with the `MetadataReceived` stop condition, so
+    /// they stop as soon as their metadata is known (which for magnet links
+    /// means it's been downloaded from peers). The other files are then set
+    /// to [`Priority::DoNotDownload`] and the torrents are started, unless
+    /// `arg` asked for them to be paused. Before Web API 2.8.15 there's no
+    /// stop condition, so the torrents are added running, may download some
+    /// unwanted data before the priorities are set, and are only paused after.
+    ///
+    /// Metadata is polled for every [`Qbit::METADATA_POLL_INTERVAL`], giving
+    /// up with [`ApiError::MetadataTimeout`] after [`Qbit::METADATA_TIMEOUT`].
+    /// Torrents that are already present are left untouched.
+    pub async fn add_torrent_selective(
+        &self,
+        arg: impl Borrow<AddTorrentArg> + Send + Sync,
+        wanted_files: Option<Vec<u64>>,
+    ) -> Result<AddOutcome> {
+        let Some(wanted_files) = wanted_files else {
+            return self.add_torrent_checked(arg).await;
+        };
+
+        let arg = arg.borrow();
+        let stop_condition = self.webapi_version_at_least("2.8.15").await? != Some(false);
+        // A stopped torrent never downloads its metadata, so it's only stopped
+        // once the metadata is received, or not at all
+        let outcome = self
+            .add_torrent_checked(AddTorrentArg {
+                paused: None,
+                stopped: None,
+                stop_condition: stop_condition.then(|| "MetadataReceived".to_owned()),
+                ..arg.clone()
+            })
+            .await?;
+        let AddOutcome::Added(hashes) = &outcome else {
+            return Ok(outcome);
+        };
+
+        for hash in hashes {
+            let contents = self.wait_for_metadata(hash, Self::METADATA_TIMEOUT).await?;
+            let unwanted = contents
+                .iter()
+                .filter(|content| !wanted_files.contains(&content.index))
+                .map(|content| content.index as i64)
+                .collect::<Vec<_>>();
+            if !unwanted.is_empty() {
+                self.set_file_priority(hash, unwanted, Priority::DoNotDownload)
+                    .await?;
+            }
+        }
+
+        let keep_stopped = [&arg.paused, &arg.stopped]
+            .iter()
+            .any(|flag| flag.as_deref() == Some("true"));
+        // `torrents/resume` was renamed to `torrents/start` in qBittorrent 5
+        let renamed = self.webapi_version_at_least("2.11.0").await? == Some(true);
+        match (keep_stopped, stop_condition) {
+            (false, true) if renamed => self
+                .post("torrents/start", Some(&HashesArg::new(hashes.clone())))
+                .await?
+                .end()?,
+            (false, true) => self.resume_torrents(hashes.clone()).await?,
+            (true, false) => self.pause_torrents(hashes.clone()).await?,
+            _ => {}
+        }
+        Ok(outcome)
+    }
+
+    /// Poll the files of a torrent until they're known, or until `timeout`
+    /// passes
+    async fn wait_for_metadata(
+        &self,
+        hash: &str,
+        timeout: Duration,
+    ) -> Result<Vec<TorrentContent>> {
+        let poll = async {
+            loop {
+                let contents = self.get_torrent_contents(hash, None).await?;
+                if !contents.is_empty() {
+                    return Ok(contents);
+                }
+                tokio::time::sleep(Self::METADATA_POLL_INTERVAL).await;
+            }
+        };
+        tokio::time::timeout(timeout, poll)
+            .await
+            .unwrap_or_else(|_| {
+                Err(Error::ApiError(ApiError::MetadataTimeout {
+                    hash: hash.to_owned(),
+                }))
+            })
+    }
+
+    /// Reannounce only the torrents among `hashes` whose next announce is due
+    /// within `min_interval`, and return their hashes.
+    ///
+    /// qBittorrent doesn't tell when a tracker was last announced to, only the
+    /// countdown to the next announce (see
+    /// [`TorrentProperty::reannounce_due`]). Skipping torrents with a
+    /// longer countdown means no announce is moved forward by more than
+    /// `min_interval`, which keeps private trackers from seeing announces
+    /// more often than they expect. The properties of every torrent are
+    /// fetched one by one.
+    pub async fn reannounce_with_min_interval(
+        &self,
+        hashes: impl Into<Hashes> + Send + Sync,
+        min_interval: Duration,
+    ) -> Result<Vec<String>> {
+        let mut due = Vec::new();
+        for hash in self.resolve_hashes(hashes).await? {
+            if self
+                .get_torrent_properties(&hash)
+                .await?
+                .reannounce_due(min_interval)
+            {
+                due.push(hash);
+            }
+        }
+
+        if !due.is_empty() {
+            self.reannounce_torrents(due.clone()).await?;
+        }
+        Ok(due)
     }
 
+    /// Add torrents from URLs or torrent files. Never resent automatically, see
+    /// [`Qbit#retries`].
+    ///
+    /// Returns [`ApiError::TorrentAddFailed`] when qBittorrent rejects the
+    /// torrent files (`415 Unsupported Media Type`) or answers `Fails.`
+    /// because none of the torrents could be added.
     pub async fn add_torrent(&self, arg: impl Borrow<AddTorrentArg> + Send + Sync) -> Result<()> {
         let a: &AddTorrentArg = arg.borrow();
-        match &a.source {
+        if a.source.is_empty() {
+            return Err(Error::EmptyInput { param: "source" });
+        }
+
+        let res = match &a.source {
             TorrentSource::Urls { urls: _ } => {
-                self.post("torrents/add", Some(arg.borrow())).await?.end()
+                self.post("torrents/add", Some(arg.borrow())).await?
             }
             TorrentSource::TorrentFiles { torrents } => {
-                for i in 0..3 {
-                    // If it's not the first attempt, we need to re-login
-                    self.login(i != 0).await?;
+                let fields = serde_json::to_value(a)?;
+                self.send(Method::POST, "torrents/add", |req| {
                     // Create a multipart form containing the torrent files and other arguments
                     let form = torrents.iter().fold(
-                        serde_json::to_value(a)?
-                            .as_object()
-                            .unwrap()
-                            .into_iter()
-                            .fold(reqwest::multipart::Form::new(), |form, (k, v)| {
+                        fields.as_object().unwrap().into_iter().fold(
+                            reqwest::multipart::Form::new(),
+                            |form, (k, v)| {
                                 // If we directly call to_string() on a Value containing a string like "hello",
                                 // it will include the quotes: "\"hello\"".
                                 // We need to use as_str() first to get the inner string without quotes.
@@ -523,7 +1299,8 @@ impl Qbit {
                                     None => v.to_string(),
                                 };
                                 form.text(k.to_string(), v.to_string())
-                            }),
+                            },
+                        ),
                         |mut form, torrent| {
                             let p = reqwest::multipart::Part::bytes(torrent.data.clone())
                                 .file_name(torrent.filename.to_string())
@@ -533,39 +1310,12 @@ impl Qbit {
                             form
                         },
                     );
-                    let req = self
-                        .client
-                        .request(Method::POST, self.url("torrents/add"))
-                        .multipart(form)
-                        .header(header::COOKIE, {
-                            self.state()
-                                .as_cookie()
-                                .expect("Cookie should be set after login")
-                        });
-
-                    trace!(request = ?req, "Sending request");
-                    let res = req
-                        .send()
-                        .await?
-                        .map_status(|code| match code as _ {
-                            StatusCode::FORBIDDEN => Some(Error::ApiError(ApiError::NotLoggedIn)),
-                            _ => None,
-                        })
-                        .tap_ok(|response| trace!(?response));
-
-                    match res {
-                        Err(Error::ApiError(ApiError::NotLoggedIn)) => {
-                            // Retry
-                            warn!("Cookie is not valid, retrying");
-                        }
-                        Err(e) => return Err(e),
-                        Ok(t) => return t.end(),
-                    }
-                }
-
-                Err(Error::ApiError(ApiError::NotLoggedIn))
+                    req.multipart(form)
+                })
+                .await?
             }
-        }
+        };
+        check_add_response(res).await
     }
 
     pub async fn add_trackers(
@@ -593,6 +1343,26 @@ impl Qbit {
         .map_err(Into::into)
     }
 
+    /// Same as [`Qbit::add_trackers`], but checks that every URL uses a scheme
+    /// supported for trackers (`http`, `https` or `udp`). Otherwise
+    /// [`Error::UnsupportedTrackerScheme`] is returned without sending the
+    /// request, since qBittorrent would silently ignore it.
+    pub async fn add_tracker_urls(
+        &self,
+        hash: impl AsRef<str> + Send + Sync,
+        urls: impl IntoIterator<Item = Url> + Send + Sync,
+    ) -> Result<()> {
+        let urls = urls
+            .into_iter()
+            .map(|url| match url.scheme() {
+                "http" | "https" | "udp" => Ok(url.to_string()),
+                _ => Err(Error::UnsupportedTrackerScheme { url }),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        self.add_trackers(hash, urls).await
+    }
+
     pub async fn edit_trackers(
         &self,
         hash: impl AsRef<str> + Send + Sync,
@@ -629,18 +1399,33 @@ impl Qbit {
         &self,
         hash: impl AsRef<str> + Send + Sync,
         urls: impl Into<Sep<Url, '|'>> + Send + Sync,
+    ) -> Result<()> {
+        self.remove_trackers_ref(hash, urls.into().as_slice()).await
+    }
+
+    /// Same as [`Qbit::remove_trackers`], but borrows the URLs instead of
+    /// taking ownership of them.
+    pub async fn remove_trackers_ref(
+        &self,
+        hash: impl AsRef<str> + Send + Sync,
+        urls: &[Url],
     ) -> Result<()> {
         #[derive(Serialize)]
         struct Arg<'a> {
             hash: &'a str,
-            urls: Sep<Url, '|'>,
+            urls: SepRef<'a, Url, '|'>,
+        }
+
+        let urls = SepRef::from(urls);
+        if urls.is_empty() {
+            return Err(Error::EmptyInput { param: "urls" });
         }
 
         self.post(
             "torrents/removeTrackers",
             Some(&Arg {
                 hash: hash.as_ref(),
-                urls: urls.into(),
+                urls,
             }),
         )
         .await
@@ -662,7 +1447,7 @@ impl Qbit {
         self.post(
             "torrents/addPeers",
             Some(&AddPeersArg {
-                hash: hashes.into().to_string(),
+                hash: non_empty_hashes(hashes)?.to_string(),
                 peers: peers.into(),
             }),
         )
@@ -679,58 +1464,76 @@ impl Qbit {
         .end()
     }
 
+    /// Move torrents one position up in the queue. Never resent automatically,
+    /// see [`Qbit#retries`].
     pub async fn increase_priority(&self, hashes: impl Into<Hashes> + Send + Sync) -> Result<()> {
-        self.post("torrents/increasePrio", Some(&HashesArg::new(hashes)))
-            .await?
-            .map_status(|c| {
-                if c == StatusCode::CONFLICT {
-                    Some(Error::ApiError(ApiError::QueueingDisabled))
-                } else {
-                    None
-                }
-            })?;
+        self.post(
+            "torrents/increasePrio",
+            Some(&HashesArg::new(non_empty_hashes(hashes)?)),
+        )
+        .await?
+        .map_status(|c| {
+            if c == StatusCode::CONFLICT {
+                Some(Error::ApiError(ApiError::QueueingDisabled))
+            } else {
+                None
+            }
+        })?;
         Ok(())
     }
 
+    /// Move torrents one position down in the queue. Never resent
+    /// automatically, see [`Qbit#retries`].
     pub async fn decrease_priority(&self, hashes: impl Into<Hashes> + Send + Sync) -> Result<()> {
-        self.post("torrents/decreasePrio", Some(&HashesArg::new(hashes)))
-            .await?
-            .map_status(|c| {
-                if c == StatusCode::CONFLICT {
-                    Some(Error::ApiError(ApiError::QueueingDisabled))
-                } else {
-                    None
-                }
-            })?;
+        self.post(
+            "torrents/decreasePrio",
+            Some(&HashesArg::new(non_empty_hashes(hashes)?)),
+        )
+        .await?
+        .map_status(|c| {
+            if c == StatusCode::CONFLICT {
+                Some(Error::ApiError(ApiError::QueueingDisabled))
+            } else {
+                None
+            }
+        })?;
         Ok(())
     }
 
     pub async fn maximal_priority(&self, hashes: impl Into<Hashes> + Send + Sync) -> Result<()> {
-        self.post("torrents/topPrio", Some(&HashesArg::new(hashes)))
-            .await?
-            .map_status(|c| {
-                if c == StatusCode::CONFLICT {
-                    Some(Error::ApiError(ApiError::QueueingDisabled))
-                } else {
-                    None
-                }
-            })?;
+        self.post(
+            "torrents/topPrio",
+            Some(&HashesArg::new(non_empty_hashes(hashes)?)),
+        )
+        .await?
+        .map_status(|c| {
+            if c == StatusCode::CONFLICT {
+                Some(Error::ApiError(ApiError::QueueingDisabled))
+            } else {
+                None
+            }
+        })?;
         Ok(())
     }
 
     pub async fn minimal_priority(&self, hashes: impl Into<Hashes> + Send + Sync) -> Result<()> {
-        self.post("torrents/bottomPrio", Some(&HashesArg::new(hashes)))
-            .await?
-            .map_status(|c| {
-                if c == StatusCode::CONFLICT {
-                    Some(Error::ApiError(ApiError::QueueingDisabled))
-                } else {
-                    None
-                }
-            })?;
+        self.post(
+            "torrents/bottomPrio",
+            Some(&HashesArg::new(non_empty_hashes(hashes)?)),
+        )
+        .await?
+        .map_status(|c| {
+            if c == StatusCode::CONFLICT {
+                Some(Error::ApiError(ApiError::QueueingDisabled))
+            } else {
+                None
+            }
+        })?;
         Ok(())
     }
 
+    /// Set the priority of files of a torrent. `indexes` are the
+    /// [`TorrentContent::index`] of the files.
     pub async fn set_file_priority(
         &self,
         hash: impl AsRef<str> + Send + Sync,
@@ -744,17 +1547,22 @@ impl Qbit {
             priority: Priority,
         }
 
+        let indexes = indexes.into();
+        if indexes.is_empty() {
+            return Err(Error::EmptyInput { param: "indexes" });
+        }
+
         self.post(
             "torrents/filePrio",
             Some(&SetFilePriorityArg {
                 hash: hash.as_ref(),
-                id: indexes.into(),
+                id: indexes,
                 priority,
             }),
         )
         .await?
         .map_status(|c| match c {
-            StatusCode::BAD_REQUEST => panic!("Invalid priority or id. This is a bug."),
+            StatusCode::BAD_REQUEST => Some(Error::ApiError(ApiError::InvalidFilePriorityOrId)),
             StatusCode::NOT_FOUND => Some(Error::ApiError(ApiError::TorrentNotFound)),
             StatusCode::CONFLICT => Some(Error::ApiError(ApiError::MetaNotDownloadedOrIdNotFound)),
             _ => None,
@@ -787,7 +1595,7 @@ impl Qbit {
         self.post(
             "torrents/downloadLimit",
             Some(&Arg {
-                hashes: hashes.into().to_string(),
+                hashes: non_empty_hashes(hashes)?.to_string(),
                 limit,
             }),
         )
@@ -795,15 +1603,69 @@ impl Qbit {
         .end()
     }
 
+    /// Set share limits of torrents. At least one limit must be set, otherwise
+    /// [`Error::EmptyInput`] is returned without sending the request.
     pub async fn set_torrent_shared_limit(
         &self,
         arg: impl Borrow<SetTorrentSharedLimitArg> + Send + Sync,
     ) -> Result<()> {
+        check_share_limits(arg.borrow())?;
+
         self.post("torrents/setShareLimits", Some(arg.borrow()))
             .await?
             .end()
     }
 
+    /// Remove every share limit of torrents, including the global ones they
+    /// would otherwise fall back to, by setting all limits to
+    /// [`RatioLimit::NoLimit`] and [`SeedingTimeLimit::NoLimit`].
+    pub async fn clear_share_limits(&self, hashes: impl Into<Hashes> + Send + Sync) -> Result<()> {
+        self.set_torrent_shared_limit(SetTorrentSharedLimitArg {
+            hashes: hashes.into(),
+            ratio_limit: Some(RatioLimit::NoLimit),
+            seeding_time_limit: Some(SeedingTimeLimit::NoLimit),
+            inactive_seeding_time_limit: Some(SeedingTimeLimit::NoLimit),
+        })
+        .await
+    }
+
+    /// Set share limits, then read the limits of the affected torrents back to
+    /// check whether they were actually applied. qBittorrent may silently
+    /// ignore the request for some torrents, which this reports as
+    /// [`ShareLimitsReport::not_applied`].
+    pub async fn set_and_verify_share_limits(
+        &self,
+        arg: impl Borrow<SetTorrentSharedLimitArg> + Send + Sync,
+    ) -> Result<ShareLimitsReport> {
+        let arg = arg.borrow();
+        check_share_limits(arg)?;
+        let hashes = self.resolve_hashes(arg.hashes.clone()).await?;
+
+        self.set_torrent_shared_limit(arg).await?;
+
+        let torrents = self
+            .get_torrent_list(GetTorrentListArg {
+                hashes: Some(Sep::<_, '|'>::from(hashes.clone()).to_string()),
+                ..Default::default()
+            })
+            .await?;
+
+        let mut report = ShareLimitsReport::default();
+        for hash in hashes {
+            let applied = torrents
+                .iter()
+                .find(|t| t.hash.as_ref() == Some(&hash))
+                .is_some_and(|t| arg.is_applied_to(t));
+            if applied {
+                report.applied.push(hash);
+            } else {
+                report.not_applied.push(hash);
+            }
+        }
+
+        Ok(report)
+    }
+
     pub async fn get_torrent_upload_limit(
         &self,
         hashes: impl Into<Hashes> + Send + Sync,
@@ -829,7 +1691,7 @@ impl Qbit {
         self.post(
             "torrents/uploadLimit",
             Some(&Arg {
-                hashes: hashes.into().to_string(),
+                hashes: non_empty_hashes(hashes)?.to_string(),
                 limit,
             }),
         )
@@ -848,11 +1710,13 @@ impl Qbit {
             location: &'a Path,
         }
 
+        let location = NonEmptyPath::new(location).ok_or(Error::EmptyPath)?;
+
         self.post(
             "torrents/setLocation",
             Some(&Arg {
-                hashes: hashes.into().to_string(),
-                location: location.as_ref(),
+                hashes: non_empty_hashes(hashes)?.to_string(),
+                location: location.as_path(),
             }),
         )
         .await?
@@ -886,12 +1750,16 @@ impl Qbit {
         .await?
         .map_status(|c| match c {
             StatusCode::NOT_FOUND => Some(Error::ApiError(ApiError::TorrentNotFound)),
-            StatusCode::CONFLICT => panic!("Name should not be empty. This is a bug."),
+            // The name is known to be non-empty, but some versions also reject
+            // invalid names with 409
+            StatusCode::CONFLICT => Some(Error::ApiError(ApiError::InvalidTorrentName)),
             _ => None,
         })?
         .end()
     }
 
+    /// Set the category of torrents. An empty `category` removes the category,
+    /// prefer [`Qbit::clear_torrent_category`] to make that explicit.
     pub async fn set_torrent_category(
         &self,
         hashes: impl Into<Hashes> + Send + Sync,
@@ -906,7 +1774,7 @@ impl Qbit {
         self.post(
             "torrents/setCategory",
             Some(&Arg {
-                hashes: hashes.into().to_string(),
+                hashes: non_empty_hashes(hashes)?.to_string(),
                 category: category.as_ref(),
             }),
         )
@@ -921,12 +1789,30 @@ impl Qbit {
         .end()
     }
 
+    /// Remove the category of torrents. Same as calling
+    /// [`Qbit::set_torrent_category`] with an empty category.
+    pub async fn clear_torrent_category(
+        &self,
+        hashes: impl Into<Hashes> + Send + Sync,
+    ) -> Result<()> {
+        self.set_torrent_category(hashes, "").await
+    }
+
+    /// Get all categories. Cached if enabled with
+    /// [`QbitBuilder::cache_metadata`].
     pub async fn get_categories(&self) -> Result<HashMap<String, Category>> {
-        self.get("torrents/categories")
-            .await?
-            .json()
-            .await
-            .map_err(Into::into)
+        let cache = self.cache.as_ref().map(|cache| &cache.categories);
+        if let Some(categories) = cache.and_then(Entry::get) {
+            return Ok(categories);
+        }
+
+        let generation = cache.map(Entry::generation);
+        let categories: HashMap<String, Category> =
+            self.get("torrents/categories").await?.json().await?;
+        if let (Some(cache), Some(generation)) = (cache, generation) {
+            cache.set(generation, categories.clone());
+        }
+        Ok(categories)
     }
 
     pub async fn add_category<T: AsRef<str> + Send + Sync>(
@@ -965,7 +1851,7 @@ impl Qbit {
         }
 
         self.post(
-            "torrents/createCategory",
+            "torrents/editCategory",
             Some(&Arg {
                 category: category.as_str(),
                 save_path: save_path.as_ref(),
@@ -1015,7 +1901,7 @@ impl Qbit {
         self.post(
             "torrents/addTags",
             Some(&Arg {
-                hashes: hashes.into().to_string(),
+                hashes: non_empty_hashes(hashes)?.to_string(),
                 tags: &tags.into().to_string(),
             }),
         )
@@ -1038,7 +1924,7 @@ impl Qbit {
         self.post(
             "torrents/removeTags",
             Some(&Arg {
-                hashes: hashes.into().to_string(),
+                hashes: non_empty_hashes(hashes)?.to_string(),
                 tags: tags.map(|t| t.into().to_string()),
             }),
         )
@@ -1046,40 +1932,58 @@ impl Qbit {
         .end()
     }
 
+    /// Get all tags. Cached if enabled with [`QbitBuilder::cache_metadata`].
     pub async fn get_all_tags(&self) -> Result<Vec<String>> {
-        self.get("torrents/tags")
-            .await?
-            .json()
-            .await
-            .map_err(Into::into)
+        let cache = self.cache.as_ref().map(|cache| &cache.tags);
+        if let Some(tags) = cache.and_then(Entry::get) {
+            return Ok(tags);
+        }
+
+        let generation = cache.map(Entry::generation);
+        let tags: Vec<String> = self.get("torrents/tags").await?.json().await?;
+        if let (Some(cache), Some(generation)) = (cache, generation) {
+            cache.set(generation, tags.clone());
+        }
+        Ok(tags)
     }
 
+    /// Create new tags. Each tag must be non-empty and must not contain a
+    /// comma, otherwise [`Error::InvalidTagName`] is returned without sending
+    /// the request.
     pub async fn create_tags(&self, tags: impl Into<Sep<String, ','>> + Send + Sync) -> Result<()> {
         #[derive(Serialize)]
         struct Arg {
             tags: String,
         }
 
+        let tags = tags.into();
+        check_tag_names(&tags)?;
+
         self.post(
             "torrents/createTags",
             Some(&Arg {
-                tags: tags.into().to_string(),
+                tags: tags.to_string(),
             }),
         )
         .await?
         .end()
     }
 
+    /// Delete tags. Tag names are validated the same way as in
+    /// [`Qbit::create_tags`].
     pub async fn delete_tags(&self, tags: impl Into<Sep<String, ','>> + Send + Sync) -> Result<()> {
         #[derive(Serialize)]
         struct Arg {
             tags: String,
         }
 
+        let tags = tags.into();
+        check_tag_names(&tags)?;
+
         self.post(
             "torrents/deleteTags",
             Some(&Arg {
-                tags: tags.into().to_string(),
+                tags: tags.to_string(),
             }),
         )
         .await?
@@ -1100,7 +2004,7 @@ impl Qbit {
         self.post(
             "torrents/setAutoManagement",
             Some(&Arg {
-                hashes: hashes.into().to_string(),
+                hashes: non_empty_hashes(hashes)?.to_string(),
                 enable,
             }),
         )
@@ -1108,30 +2012,68 @@ impl Qbit {
         .end()
     }
 
+    /// Toggle sequential download of torrents. Never resent automatically, see
+    /// [`Qbit#retries`].
     pub async fn toggle_sequential_download(
         &self,
         hashes: impl Into<Hashes> + Send + Sync,
     ) -> Result<()> {
         self.post(
             "torrents/toggleSequentialDownload",
-            Some(&HashesArg::new(hashes)),
+            Some(&HashesArg::new(non_empty_hashes(hashes)?)),
         )
         .await?
         .end()
     }
 
+    /// Toggle first and last piece priority of torrents. Never resent
+    /// automatically, see [`Qbit#retries`].
     pub async fn toggle_first_last_piece_priority(
         &self,
         hashes: impl Into<Hashes> + Send + Sync,
     ) -> Result<()> {
         self.post(
             "torrents/toggleFirstLastPiecePrio",
-            Some(&HashesArg::new(hashes)),
+            Some(&HashesArg::new(non_empty_hashes(hashes)?)),
         )
         .await?
         .end()
     }
 
+    /// Enable or disable first and last piece priority, then read it back.
+    /// Only torrents not already in the desired state are toggled. Returns the
+    /// hashes of the torrents where it didn't apply, e.g. because they were
+    /// not found or qBittorrent ignored the change.
+    pub async fn set_first_last_piece_priority_verified(
+        &self,
+        hashes: impl Into<Hashes> + Send + Sync,
+        enabled: bool,
+    ) -> Result<Vec<String>> {
+        let hashes = self.resolve_hashes(hashes).await?;
+        let to_toggle = self
+            .torrents_by_hashes(&hashes)
+            .await?
+            .into_iter()
+            .filter(|torrent| torrent.f_l_piece_prio != Some(enabled))
+            .filter_map(|torrent| torrent.hash)
+            .collect::<Vec<_>>();
+        if !to_toggle.is_empty() {
+            self.toggle_first_last_piece_priority(to_toggle).await?;
+        }
+
+        let applied = self
+            .torrents_by_hashes(&hashes)
+            .await?
+            .into_iter()
+            .filter(|torrent| torrent.f_l_piece_prio == Some(enabled))
+            .filter_map(|torrent| torrent.hash)
+            .collect::<Vec<_>>();
+        Ok(hashes
+            .into_iter()
+            .filter(|hash| !applied.contains(hash))
+            .collect())
+    }
+
     pub async fn set_force_start(
         &self,
         hashes: impl Into<Hashes> + Send + Sync,
@@ -1146,7 +2088,7 @@ impl Qbit {
         self.post(
             "torrents/setForceStart",
             Some(&Arg {
-                hashes: hashes.into().to_string(),
+                hashes: non_empty_hashes(hashes)?.to_string(),
                 value,
             }),
         )
@@ -1168,7 +2110,7 @@ impl Qbit {
         self.post(
             "torrents/setSuperSeeding",
             Some(&Arg {
-                hashes: hashes.into().to_string(),
+                hashes: non_empty_hashes(hashes)?.to_string(),
                 value,
             }),
         )
@@ -1176,6 +2118,29 @@ impl Qbit {
         .end()
     }
 
+    /// Rename files of a torrent with [`Qbit::rename_file`], one after
+    /// another in the given order so that a rename may refer to the path left
+    /// by a previous one. A failed rename doesn't stop the following ones, the
+    /// result of each is returned along with its old path. No renames returns
+    /// [`Error::EmptyInput`].
+    pub async fn rename_files(
+        &self,
+        hash: impl AsRef<str> + Send + Sync,
+        renames: Vec<(PathBuf, PathBuf)>,
+    ) -> Result<Vec<(PathBuf, Result<()>)>> {
+        if renames.is_empty() {
+            return Err(Error::EmptyInput { param: "renames" });
+        }
+
+        let hash = hash.as_ref();
+        let mut results = Vec::with_capacity(renames.len());
+        for (old_path, new_path) in renames {
+            let res = self.rename_file(hash, &old_path, new_path).await;
+            results.push((old_path, res));
+        }
+        Ok(results)
+    }
+
     pub async fn rename_file(
         &self,
         hash: impl AsRef<str> + Send + Sync,
@@ -1190,12 +2155,15 @@ impl Qbit {
             new_path: &'a Path,
         }
 
+        let old_path = NonEmptyPath::new(old_path).ok_or(Error::EmptyPath)?;
+        let new_path = NonEmptyPath::new(new_path).ok_or(Error::EmptyPath)?;
+
         self.post(
             "torrents/renameFile",
             Some(&Arg {
                 hash: hash.as_ref(),
-                old_path: old_path.as_ref(),
-                new_path: new_path.as_ref(),
+                old_path: old_path.as_path(),
+                new_path: new_path.as_path(),
             }),
         )
         .await?
@@ -1223,12 +2191,15 @@ impl Qbit {
             new_path: &'a Path,
         }
 
+        let old_path = NonEmptyPath::new(old_path).ok_or(Error::EmptyPath)?;
+        let new_path = NonEmptyPath::new(new_path).ok_or(Error::EmptyPath)?;
+
         self.post(
             "torrents/renameFolder",
             Some(&Arg {
                 hash: hash.as_ref(),
-                old_path: old_path.as_ref(),
-                new_path: new_path.as_ref(),
+                old_path: old_path.as_path(),
+                new_path: new_path.as_path(),
             }),
         )
         .await?
@@ -1399,34 +2370,404 @@ impl Qbit {
         .end()
     }
 
-    fn url(&self, path: &'static str) -> Url {
-        self.endpoint
-            .join("api/v2/")
-            .unwrap()
-            .join(path)
-            .expect("Invalid API endpoint")
+    /// Start a search job and return its ID. `plugins` can be `all`, `enabled`
+    /// or a list of plugin names. Never resent automatically, see
+    /// [`Qbit#retries`].
+    pub async fn start_search(
+        &self,
+        pattern: impl AsRef<str> + Send + Sync,
+        plugins: impl Into<Sep<String, '|'>> + Send + Sync,
+        category: impl Into<Option<SearchCategory>> + Send + Sync,
+    ) -> Result<u64> {
+        #[derive(Serialize)]
+        struct Arg<'a> {
+            pattern: &'a str,
+            plugins: String,
+            category: SearchCategory,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct Id {
+            id: u64,
+        }
+
+        self.post(
+            "search/start",
+            Some(&Arg {
+                pattern: pattern.as_ref(),
+                plugins: plugins.into().to_string(),
+                category: category.into().unwrap_or_default(),
+            }),
+        )
+        .await?
+        .map_status(|c| {
+            if c == StatusCode::CONFLICT {
+                Some(Error::ApiError(ApiError::TooManySearches))
+            } else {
+                None
+            }
+        })?
+        .json::<Id>()
+        .await
+        .map(|Id { id }| id)
+        .map_err(Into::into)
+    }
+
+    pub async fn stop_search(&self, id: u64) -> Result<()> {
+        self.post("search/stop", Some(&SearchIdArg { id }))
+            .await?
+            .map_status(SEARCH_NOT_FOUND)?
+            .end()
+    }
+
+    /// Get the status of a search job, or of all search jobs if `id` is
+    /// `None`.
+    pub async fn get_search_status(
+        &self,
+        id: impl Into<Option<u64>> + Send + Sync,
+    ) -> Result<Vec<SearchStatus>> {
+        #[derive(Serialize)]
+        #[skip_serializing_none]
+        struct Arg {
+            id: Option<u64>,
+        }
+
+        self.get_with("search/status", &Arg { id: id.into() })
+            .await?
+            .map_status(SEARCH_NOT_FOUND)?
+            .json()
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Get results of a search job. A negative `offset` counts from the end.
+    pub async fn get_search_results(
+        &self,
+        id: u64,
+        limit: impl Into<Option<u64>> + Send + Sync,
+        offset: impl Into<Option<i64>> + Send + Sync,
+    ) -> Result<SearchResults> {
+        #[derive(Serialize)]
+        #[skip_serializing_none]
+        struct Arg {
+            id: u64,
+            limit: Option<u64>,
+            offset: Option<i64>,
+        }
+
+        self.get_with(
+            "search/results",
+            &Arg {
+                id,
+                limit: limit.into(),
+                offset: offset.into(),
+            },
+        )
+        .await?
+        .map_status(|c| match c {
+            StatusCode::NOT_FOUND => Some(Error::ApiError(ApiError::SearchJobNotFound)),
+            StatusCode::CONFLICT => Some(Error::ApiError(ApiError::InvalidSearchOffset)),
+            _ => None,
+        })?
+        .json()
+        .await
+        .map_err(Into::into)
+    }
+
+    pub async fn delete_search(&self, id: u64) -> Result<()> {
+        self.post("search/delete", Some(&SearchIdArg { id }))
+            .await?
+            .map_status(SEARCH_NOT_FOUND)?
+            .end()
+    }
+
+    /// Start a search and stream its results as they arrive. Results are
+    /// polled every `poll` interval until the search job stops and all results
+    /// have been yielded, after which the search job is deleted. Deleting the
+    /// job is best-effort: a failure is only logged.
+    #[cfg(feature = "stream")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "stream")))]
+    pub fn search_stream(
+        &self,
+        pattern: impl Into<String>,
+        plugins: impl Into<Sep<String, '|'>>,
+        category: impl Into<Option<SearchCategory>>,
+        poll: std::time::Duration,
+    ) -> impl futures_util::Stream<Item = Result<SearchResult>> + Send + '_ {
+        use std::collections::VecDeque;
+
+        struct State {
+            search: Option<(String, Sep<String, '|'>, Option<SearchCategory>)>,
+            id: Option<u64>,
+            offset: u64,
+            buffer: VecDeque<SearchResult>,
+            done: bool,
+        }
+
+        let state = State {
+            search: Some((pattern.into(), plugins.into(), category.into())),
+            id: None,
+            offset: 0,
+            buffer: VecDeque::new(),
+            done: false,
+        };
+
+        futures_util::stream::unfold(state, move |mut state| async move {
+            loop {
+                if let Some(result) = state.buffer.pop_front() {
+                    return Some((Ok(result), state));
+                }
+                if state.done {
+                    return None;
+                }
+
+                let id = match (state.id, state.search.take()) {
+                    (Some(id), _) => {
+                        tokio::time::sleep(poll).await;
+                        id
+                    }
+                    (None, Some((pattern, plugins, category))) => {
+                        match self.start_search(pattern, plugins, category).await {
+                            Ok(id) => *state.id.insert(id),
+                            Err(e) => {
+                                state.done = true;
+                                return Some((Err(e), state));
+                            }
+                        }
+                    }
+                    (None, None) => unreachable!("Search should be started exactly once"),
+                };
+
+                let res = self.get_search_results(id, None, state.offset as i64).await;
+                let finished = match res {
+                    Ok(SearchResults {
+                        results,
+                        status,
+                        total,
+                    }) => {
+                        state.offset += results.len() as u64;
+                        state.buffer.extend(results);
+                        status == SearchState::Stopped && state.offset >= total
+                    }
+                    Err(e) => {
+                        state.done = true;
+                        if let Err(e) = self.delete_search(id).await {
+                            warn!(error = ?e, id, "Failed to delete search job");
+                        }
+                        return Some((Err(e), state));
+                    }
+                };
+
+                if finished {
+                    state.done = true;
+                    if let Err(e) = self.delete_search(id).await {
+                        warn!(error = ?e, id, "Failed to delete search job");
+                    }
+                }
+            }
+        })
+    }
+
+    /// Resolve [`Hashes`] into a concrete list of torrent hashes.
+    /// [`Hashes::All`] is resolved by fetching the current torrent list.
+    pub async fn resolve_hashes(
+        &self,
+        hashes: impl Into<Hashes> + Send + Sync,
+    ) -> Result<Vec<String>> {
+        match hashes.into() {
+            Hashes::Hashes(hashes) => Ok(hashes.as_slice().to_vec()),
+            Hashes::All => self
+                .get_torrent_list(GetTorrentListArg::default())
+                .await?
+                .into_iter()
+                .filter_map(|torrent| torrent.hash)
+                .collect::<Vec<_>>()
+                .pipe(Ok),
+        }
+    }
+
+    /// Resolve `hashes` like [`Qbit::resolve_hashes`] and leave out the ones in
+    /// `exclude`, e.g. `Hashes::All` to get all torrents but some. qBittorrent
+    /// has no way to exclude torrents, so the result is always a concrete
+    /// [`Hashes::Hashes`], which is empty if every torrent is excluded.
+    pub async fn resolve_hashes_except(
+        &self,
+        hashes: impl Into<Hashes> + Send + Sync,
+        exclude: &[String],
+    ) -> Result<Hashes> {
+        self.resolve_hashes(hashes)
+            .await?
+            .into_iter()
+            .filter(|hash| !exclude.contains(hash))
+            .collect::<Vec<_>>()
+            .pipe(Hashes::from)
+            .pipe(Ok)
+    }
+
+    /// Fail with [`ApiError::EndpointUnavailable`] if the Web API version is
+    /// older than `webapi_version`, i.e. `endpoint` was added in qBittorrent
+    /// `since_version`. The version is requested once and cached. Unknown
+    /// version formats are let through for the server to decide.
+    async fn require_webapi_version(
+        &self,
+        endpoint: &'static str,
+        webapi_version: &'static str,
+        since_version: &'static str,
+    ) -> Result<()> {
+        match self.webapi_version_at_least(webapi_version).await? {
+            Some(false) => Err(Error::ApiError(ApiError::EndpointUnavailable {
+                endpoint,
+                since_version,
+            })),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the Web API version is at least `required`, or `None` if the
+    /// version format is unknown. The version is requested once and cached.
+    async fn webapi_version_at_least(&self, required: &str) -> Result<Option<bool>> {
+        let version = self
+            .webapi_version
+            .get_or_try_init(|| self.get_webapi_version())
+            .await?;
+        let parse = |version: &str| {
+            version
+                .trim_start_matches('v')
+                .split('.')
+                .map(str::parse::<u32>)
+                .collect::<Result<Vec<_>, _>>()
+                .ok()
+        };
+
+        Ok(match (parse(version), parse(required)) {
+            (Some(version), Some(required)) => Some(version >= required),
+            _ => None,
+        })
+    }
+
+    /// `includeTrackers` makes older versions reject the whole request
+    async fn check_torrent_list_arg(&self, arg: &GetTorrentListArg) -> Result<()> {
+        if arg.include_trackers == Some(true) {
+            self.require_webapi_version("torrents/info?includeTrackers", "2.11.4", "5.1.0")
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Torrents among `hashes` that currently exist
+    async fn torrents_by_hashes(&self, hashes: &[String]) -> Result<Vec<Torrent>> {
+        // An empty filter would match all torrents
+        if hashes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.get_torrent_list(GetTorrentListArg {
+            hashes: Some(Sep::<_, '|'>::from(hashes).to_string()),
+            ..Default::default()
+        })
+        .await?
+        .into_iter()
+        .filter(|torrent| {
+            torrent
+                .hash
+                .as_ref()
+                .is_some_and(|hash| hashes.contains(hash))
+        })
+        .collect::<Vec<_>>()
+        .pipe(Ok)
+    }
+
+    /// Hashes among `hashes` of torrents that currently exist
+    async fn existing_hashes(&self, hashes: &[String]) -> Result<Vec<String>> {
+        self.torrents_by_hashes(hashes)
+            .await?
+            .into_iter()
+            .filter_map(|torrent| torrent.hash)
+            .collect::<Vec<_>>()
+            .pipe(Ok)
+    }
+
+    fn url(&self, path: &str) -> Url {
+        self.api_base.join(path).expect("Invalid API endpoint")
     }
 
     fn state(&self) -> MutexGuard<'_, LoginState> {
         self.state.lock().unwrap()
     }
 
+    /// Wait for a slot to send a request if concurrent requests are limited
+    async fn acquire_permit(&self) -> Option<SemaphorePermit<'_>> {
+        match &self.limiter {
+            Some(limiter) => Some(
+                limiter
+                    .acquire()
+                    .await
+                    .expect("Semaphore should never be closed"),
+            ),
+            None => None,
+        }
+    }
+
     /// Log in to qBittorrent. Set force to `true` to forcefully re-login
     /// regardless if cookie is already set.
     pub async fn login(&self, force: bool) -> Result<()> {
-        let re_login = force || { self.state().as_cookie().is_none() };
+        self.login_with_outcome(force).await.map(drop)
+    }
+
+    /// Same as [`Qbit::login`], but also reports whether a login request was
+    /// actually sent, e.g. to meter logins and avoid getting IP banned.
+    pub async fn login_with_outcome(&self, force: bool) -> Result<LoginOutcome> {
+        self.login_inner(force, None).await
+    }
+
+    /// Log in again after `rejected`, the cookie sent with a request that was
+    /// rejected. Nothing is sent if another task already replaced it.
+    async fn relogin(&self, rejected: Option<&str>) -> Result<LoginOutcome> {
+        self.login_inner(true, rejected).await
+    }
+
+    async fn login_inner(&self, force: bool, rejected: Option<&str>) -> Result<LoginOutcome> {
+        if matches!(*self.state(), LoginState::NoAuth) {
+            // Being asked to log in again means the request was rejected, and
+            // there's no way to authenticate
+            return if force {
+                Err(Error::ApiError(ApiError::NotLoggedIn))
+            } else {
+                Ok(LoginOutcome::AuthDisabled)
+            };
+        }
+
+        if !force && self.state().as_cookie().is_some() {
+            trace!("Already logged in, skipping");
+            return Ok(LoginOutcome::AlreadyLoggedIn);
+        }
+
+        let _login = self.login_lock.lock().await;
+        // Another task may have logged in before or while waiting for the lock
+        let cookie = self.state().as_cookie().map(str::to_owned);
+        let re_login = match (cookie.as_deref(), rejected) {
+            (None, _) => true,
+            (Some(cookie), Some(rejected)) => force && cookie == rejected,
+            (Some(_), None) => force,
+        };
         if re_login {
             debug!("Cookie not found, logging in");
-            self.client
+            // Without a credential (cookie provided by the user), there's no way to
+            // obtain a new cookie
+            let credential = self
+                .state()
+                .as_credential()
+                .cloned()
+                .ok_or(Error::ApiError(ApiError::NotLoggedIn))?;
+            let permit = self.acquire_permit().await;
+            let req = self
+                .client
                 .request(Method::POST, self.url("auth/login"))
-                .pipe(|req| {
-                    req.form(
-                        self.state()
-                            .as_credential()
-                            .expect("Credential should be set if cookie is not set"),
-                    )
-                })
-                .send()
+                .headers(self.headers.clone())
+                .form(&credential)
+                .build()?;
+            self.transport
+                .execute(req)
                 .await?
                 .map_status(|code| match code as _ {
                     StatusCode::FORBIDDEN => Some(Error::ApiError(ApiError::IpBanned)),
@@ -1434,81 +2775,185 @@ impl Qbit {
                 })?
                 .extract::<Cookie>()?
                 .pipe(|Cookie(cookie)| self.state.lock().unwrap().add_cookie(cookie));
+            drop(permit);
 
             debug!("Log in success");
+            Ok(LoginOutcome::Authenticated)
         } else {
             trace!("Already logged in, skipping");
+            Ok(LoginOutcome::AlreadyLoggedIn)
         }
+    }
 
-        Ok(())
+    /// Send a request to any endpoint, for APIs not covered by this crate.
+    /// `path` is relative to `/api/v2/`, e.g. `torrents/info`, and `body` is
+    /// sent as query for `GET` and as form for `POST`. Session handling and
+    /// [retries](Qbit#retries) work the same as for the built-in methods.
+    ///
+    /// The response is returned as is, use [`ResponseExt`] to map its status
+    /// like the built-in methods do:
+    ///
+    /// ```no_run
+    /// use qbit_rs::{Qbit, ResponseExt, TORRENT_NOT_FOUND};
+    /// use reqwest::Method;
+    ///
+    /// # async fn run(api: Qbit) -> Result<(), qbit_rs::Error> {
+    /// let properties: serde_json::Value = api
+    ///     .request_raw(
+    ///         Method::GET,
+    ///         "torrents/properties",
+    ///         Some(&[("hash", "8c212779b4abde7c6bc608063a0d008b7e40ce32")]),
+    ///     )
+    ///     .await?
+    ///     .map_status(TORRENT_NOT_FOUND)?
+    ///     .json()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Only `GET` and `POST` requests can have a body, other methods with
+    /// `Some` body fail with [`Error::UnsupportedMethod`].
+    pub async fn request_raw(
+        &self,
+        method: Method,
+        path: impl AsRef<str> + Send + Sync,
+        body: Option<&(impl Serialize + Sync)>,
+    ) -> Result<Response> {
+        if body.is_some() && !matches!(method, Method::GET | Method::POST) {
+            return Err(Error::UnsupportedMethod(method));
+        }
+        let path = path.as_ref();
+        // Checked here so that `Qbit::url` can't fail
+        self.api_base
+            .join(path)
+            .map_err(|e| Error::InvalidEndpoint(format!("{e:?}")))?;
+        self.request(method, path, body).await
     }
 
     async fn request(
         &self,
         method: Method,
-        path: &'static str,
+        path: &str,
         body: Option<&(impl Serialize + Sync)>,
     ) -> Result<Response> {
-        for i in 0..3 {
-            // If it's not the first attempt, we need to re-login
-            self.login(i != 0).await?;
+        self.send(method.clone(), path, |req| match body {
+            Some(body) => match method {
+                Method::GET => req.query(body),
+                Method::POST => req.form(body),
+                _ => unreachable!("Only GET and POST are supported"),
+            },
+            None => req,
+        })
+        .await
+    }
 
-            let mut req =
-                self.client
+    /// Send a request with the cookie set, logging in when needed. `build` is
+    /// called again for every attempt. See [`Qbit#retries`] for when a request
+    /// is resent.
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        build: impl Fn(RequestBuilder) -> RequestBuilder + Send + Sync,
+    ) -> Result<Response> {
+        // Cookie sent with the last attempt, if it was rejected
+        let mut rejected: Option<Option<String>> = None;
+        for _ in 0..3 {
+            match &rejected {
+                None => self.login(false).await?,
+                Some(cookie) => self.relogin(cookie.as_deref()).await.map(drop)?,
+            }
+
+            let mut rate_limited = 0;
+            let (res, cookie) = loop {
+                // Copied out so that the lock isn't held while building the
+                // request, another task may log in again meanwhile
+                let cookie = self.state().as_cookie().map(str::to_owned);
+                let req = self
+                    .client
                     .request(method.clone(), self.url(path))
-                    .header(header::COOKIE, {
-                        self.state()
-                            .as_cookie()
-                            .expect("Cookie should be set after login")
-                    });
-
-            if let Some(ref body) = body {
-                match method {
-                    Method::GET => req = req.query(body),
-                    Method::POST => req = req.form(body),
-                    _ => unreachable!("Only GET and POST are supported"),
+                    .headers(self.headers.clone())
+                    .pipe(|req| match &cookie {
+                        Some(cookie) => req.header(header::COOKIE, cookie.as_str()),
+                        // Only without authentication, otherwise login sets it
+                        None => req,
+                    })
+                    .pipe(&build);
+
+                trace!(request = ?req, "Sending request");
+                let req = req.build()?;
+                let permit = self.acquire_permit().await;
+                let res = self.transport.execute(req).await;
+                drop(permit);
+
+                // Whether the request succeeded or not, tags or categories may
+                // have changed
+                if let Some(cache) = self
+                    .cache
+                    .as_ref()
+                    .filter(|_| METADATA_ENDPOINTS.contains(&path))
+                {
+                    cache.clear();
+                }
+
+                let res = res?;
+                if res.status() != StatusCode::TOO_MANY_REQUESTS {
+                    break (res, cookie);
                 }
-            }
-            trace!(request = ?req, "Sending request");
-            let res = req
-                .send()
-                .await?
-                .map_status(|code| match code as _ {
-                    StatusCode::FORBIDDEN => Some(Error::ApiError(ApiError::NotLoggedIn)),
-                    _ => None,
-                })
-                .tap_ok(|response| trace!(?response));
 
-            match res {
-                Err(Error::ApiError(ApiError::NotLoggedIn)) => {
+                // Rejected by a proxy before reaching qBittorrent, so it's safe
+                // to resend any request
+                let retry_after = retry_after(&res);
+                if rate_limited >= self.rate_limit_retries
+                    || retry_after.is_some_and(|delay| delay > self.max_retry_after)
+                {
+                    return Err(Error::ApiError(ApiError::RateLimited { retry_after }));
+                }
+                rate_limited += 1;
+                let delay = retry_after.unwrap_or(
+                    Duration::from_secs(1 << (rate_limited - 1).min(6)).min(self.max_retry_after),
+                );
+                warn!(?delay, "Rate limited, retrying");
+                tokio::time::sleep(delay).await;
+            };
+
+            match session_checked(res).await? {
+                Some(response) => {
+                    trace!(?response);
+                    return response.read_error_message().await;
+                }
+                None if is_idempotent(&method, path) => {
                     // Retry
                     warn!("Cookie is not valid, retrying");
+                    rejected = Some(cookie);
+                }
+                None => {
+                    // The request was rejected before being processed, but
+                    // resending it is left to the caller. Refresh the cookie
+                    // so that their retry can succeed.
+                    warn!(
+                        path,
+                        "Cookie is not valid, not retrying non-idempotent request"
+                    );
+                    self.relogin(cookie.as_deref()).await?;
+                    return Err(Error::ApiError(ApiError::NotLoggedIn));
                 }
-                Err(e) => return Err(e),
-                Ok(t) => return Ok(t),
             }
         }
 
         Err(Error::ApiError(ApiError::NotLoggedIn))
     }
 
-    async fn get(&self, path: &'static str) -> Result<Response> {
+    async fn get(&self, path: &str) -> Result<Response> {
         self.request(Method::GET, path, NONE).await
     }
 
-    async fn get_with(
-        &self,
-        path: &'static str,
-        param: &(impl Serialize + Sync),
-    ) -> Result<Response> {
+    async fn get_with(&self, path: &str, param: &(impl Serialize + Sync)) -> Result<Response> {
         self.request(Method::GET, path, Some(param)).await
     }
 
-    async fn post(
-        &self,
-        path: &'static str,
-        body: Option<&(impl Serialize + Sync)>,
-    ) -> Result<Response> {
+    async fn post(&self, path: &str, body: Option<&(impl Serialize + Sync)>) -> Result<Response> {
         self.request(Method::POST, path, body).await
     }
 }
@@ -1518,14 +2963,184 @@ impl Clone for Qbit {
         let state = self.state.lock().unwrap().clone();
         Self {
             client: self.client.clone(),
+            transport: self.transport.clone(),
             endpoint: self.endpoint.clone(),
             state: Mutex::new(state),
+            login_lock: tokio::sync::Mutex::new(()),
+            limiter: self.limiter.clone(),
+            headers: self.headers.clone(),
+            cache: self.cache.clone(),
+            default_save_path: Mutex::new(self.default_save_path.lock().unwrap().clone()),
+            webapi_version: self.webapi_version.clone(),
+            rate_limit_retries: self.rate_limit_retries,
+            max_retry_after: self.max_retry_after,
+            api_base: self.api_base.clone(),
         }
     }
 }
 
 const NONE: Option<&'static ()> = Option::None;
 
+/// Endpoints that must not be resent automatically, since applying them twice
+/// differs from applying them once.
+const NON_IDEMPOTENT: &[&str] = &[
+    "torrents/add",
+    "torrents/delete",
+    "torrents/increasePrio",
+    "torrents/decreasePrio",
+    "torrents/toggleSequentialDownload",
+    "torrents/toggleFirstLastPiecePrio",
+    "transfer/toggleSpeedLimitsMode",
+    "search/start",
+];
+
+fn is_idempotent(method: &Method, path: &str) -> bool {
+    method == Method::GET || !NON_IDEMPOTENT.contains(&path)
+}
+
+/// Check whether the response is qBittorrent rejecting the session, which is
+/// a 403 with a bare `Forbidden` (or empty) body. Returns `None` in that case.
+/// Other 403s come from the endpoint itself, e.g. missing write access, and
+/// are handed back to the caller.
+async fn session_checked(res: Response) -> Result<Option<Response>> {
+    if res.status() != StatusCode::FORBIDDEN {
+        return Ok(Some(res));
+    }
+
+    let mut builder = http::Response::builder()
+        .status(StatusCode::FORBIDDEN)
+        .version(res.version())
+        .url(res.url().clone());
+    if let Some(headers) = builder.headers_mut() {
+        *headers = res.headers().clone();
+    }
+    let body = res.bytes().await?;
+    if body.is_empty() || body.as_ref() == b"Forbidden" {
+        return Ok(None);
+    }
+
+    builder
+        .body(body)
+        .map(|res| Some(res.into()))
+        .map_err(|_| Error::BadResponse {
+            explain: "Failed to rebuild forbidden response",
+        })
+}
+
+/// Delay requested by the `Retry-After` header of a `429 Too Many Requests`.
+/// Only the number of seconds form is supported.
+fn retry_after(res: &Response) -> Option<Duration> {
+    res.headers()
+        .get(header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// Turn [`ApiError::TorrentNotFound`] into `None`
+fn found<T>(res: Result<T>) -> Result<Option<T>> {
+    match res {
+        Ok(value) => Ok(Some(value)),
+        Err(Error::ApiError(ApiError::TorrentNotFound)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// qBittorrent parses `last_known_id` as a 32-bit integer and falls back to `0`
+/// for anything out of range, which would return almost every message again.
+/// Ids below `-1` mean the same as `-1` and are sent as such.
+fn normalize_last_known_id(id: Option<i64>) -> Option<i64> {
+    id.map(|id| id.clamp(-1, i32::MAX.into()))
+}
+
+/// `torrents/add` answers `415` for invalid torrent files, and `Fails.` when
+/// no torrent could be added, with a success status in older versions.
+async fn check_add_response(res: Response) -> Result<()> {
+    if res.error_message().is_some() {
+        return res.end();
+    }
+
+    let status = res.status();
+    let body = res.text().await?;
+    let body = body.trim();
+    if status == StatusCode::UNSUPPORTED_MEDIA_TYPE || body == "Fails." {
+        let reason = match body {
+            "" => "Torrent file is not valid".to_owned(),
+            body => body.to_owned(),
+        };
+        return Err(Error::ApiError(ApiError::TorrentAddFailed { reason }));
+    }
+
+    match status {
+        status if status.is_success() => Ok(()),
+        StatusCode::FORBIDDEN => Err(Error::ApiError(ApiError::NotLoggedIn)),
+        status => Err(Error::UnknownHttpCode(status)),
+    }
+}
+
+/// Whether the connection was closed before the response was complete
+fn is_connection_dropped(err: &reqwest::Error) -> bool {
+    if err.is_body() {
+        return true;
+    }
+
+    let mut source = std::error::Error::source(err);
+    while let Some(err) = source {
+        if let Some(err) = err.downcast_ref::<hyper::Error>() {
+            if err.is_incomplete_message() {
+                return true;
+            }
+        }
+        if let Some(err) = err.downcast_ref::<std::io::Error>() {
+            if err.kind() == std::io::ErrorKind::ConnectionReset {
+                return true;
+            }
+        }
+        source = err.source();
+    }
+    false
+}
+
+/// An empty list of hashes selects no torrent, so the request would silently
+/// do nothing. Use [`Hashes::All`] to select every torrent.
+fn non_empty_hashes(hashes: impl Into<Hashes>) -> Result<Hashes> {
+    let hashes = hashes.into();
+    if hashes.is_empty() {
+        return Err(Error::EmptyInput { param: "hashes" });
+    }
+    Ok(hashes)
+}
+
+/// Setting no limit at all is a no-op that qBittorrent rejects
+fn check_share_limits(arg: &SetTorrentSharedLimitArg) -> Result<()> {
+    if arg.hashes.is_empty() {
+        return Err(Error::EmptyInput { param: "hashes" });
+    }
+    if arg.ratio_limit.is_none()
+        && arg.seeding_time_limit.is_none()
+        && arg.inactive_seeding_time_limit.is_none()
+    {
+        return Err(Error::EmptyInput { param: "limits" });
+    }
+    Ok(())
+}
+
+/// Tags are sent as a comma separated list, so a tag containing a comma would
+/// silently be split into several tags by qBittorrent.
+fn check_tag_names(tags: &Sep<String, ','>) -> Result<()> {
+    match tags
+        .as_slice()
+        .iter()
+        .find(|tag| tag.is_empty() || tag.contains(','))
+    {
+        Some(tag) => Err(Error::InvalidTagName { tag: tag.clone() }),
+        None => Ok(()),
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("Http error: {0}")]
@@ -1534,12 +3149,36 @@ pub enum Error {
     #[error("API Returned bad response: {explain}")]
     BadResponse { explain: &'static str },
 
+    #[error("API returned {status}: {message}")]
+    ServerError { status: StatusCode, message: String },
+
     #[error("API returned unknown status code: {0}")]
     UnknownHttpCode(StatusCode),
 
     #[error("Non ASCII header")]
     NonAsciiHeader,
 
+    #[error("Tag name is empty or contains a comma: {tag:?}")]
+    InvalidTagName { tag: String },
+
+    #[error("Tracker URL must use http, https or udp: {url}")]
+    UnsupportedTrackerScheme { url: Url },
+
+    #[error("Port must be between 1 and 65535, got {0}")]
+    InvalidPort(u16),
+
+    #[error("Path is empty")]
+    EmptyPath,
+
+    #[error("Invalid endpoint URL: {0}")]
+    InvalidEndpoint(String),
+
+    #[error("`{0}` requests can't have a body, only `GET` and `POST` can")]
+    UnsupportedMethod(Method),
+
+    #[error("`{param}` must not be empty")]
+    EmptyInput { param: &'static str },
+
     #[error(transparent)]
     ApiError(#[from] ApiError),
 
@@ -1547,6 +3186,58 @@ pub enum Error {
     SerdeJsonError(#[from] serde_json::Error),
 }
 
+impl Error {
+    /// The torrent, category or search job was not found, or an unknown
+    /// `404 Not Found` was returned
+    pub fn is_not_found(&self) -> bool {
+        match self {
+            Error::ApiError(
+                ApiError::TorrentNotFound
+                | ApiError::CategoryNotFound
+                | ApiError::SearchJobNotFound,
+            ) => true,
+            Error::UnknownHttpCode(status) | Error::ServerError { status, .. } => {
+                *status == StatusCode::NOT_FOUND
+            }
+            _ => false,
+        }
+    }
+
+    /// Authentication failed, or the session was rejected
+    pub fn is_auth_error(&self) -> bool {
+        match self {
+            Error::ApiError(ApiError::NotLoggedIn | ApiError::IpBanned) => true,
+            Error::UnknownHttpCode(status) | Error::ServerError { status, .. } => {
+                matches!(*status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN)
+            }
+            _ => false,
+        }
+    }
+
+    /// The request couldn't be sent or the response couldn't be received, e.g.
+    /// the connection was refused or timed out
+    pub fn is_transport(&self) -> bool {
+        match self {
+            Error::HttpError(e) => {
+                e.is_connect() || e.is_timeout() || e.is_request() || e.is_body()
+            }
+            _ => false,
+        }
+    }
+
+    /// Sending the same request again later may succeed: transport errors,
+    /// rate limiting, rejected sessions and server errors (`5xx`)
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::ApiError(ApiError::RateLimited { .. } | ApiError::NotLoggedIn) => true,
+            Error::UnknownHttpCode(status) | Error::ServerError { status, .. } => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            _ => self.is_transport(),
+        }
+    }
+}
+
 /// Errors defined and returned by the API
 #[derive(Debug, thiserror::Error)]
 pub enum ApiError {
@@ -1562,6 +3253,9 @@ pub enum ApiError {
     #[error("Torrent name is empty")]
     TorrentNameEmpty,
 
+    #[error("Torrent name is empty or invalid")]
+    InvalidTorrentName,
+
     #[error("`newUrl` is not a valid URL")]
     InvalidTrackerUrl,
 
@@ -1577,6 +3271,9 @@ pub enum ApiError {
     #[error("Torrent metadata hasn't downloaded yet or at least one file id was not found")]
     MetaNotDownloadedOrIdNotFound,
 
+    #[error("Invalid file priority, or at least one file id is not an integer")]
+    InvalidFilePriorityOrId,
+
     #[error("Save path is empty")]
     SavePathEmpty,
 
@@ -1594,6 +3291,30 @@ pub enum ApiError {
 
     #[error("Invalid `newPath` or `oldPath`, or `newPath` already in use")]
     InvalidPath,
+
+    #[error("Failed to add torrent: {reason}")]
+    TorrentAddFailed { reason: String },
+
+    #[error("Search job was not found")]
+    SearchJobNotFound,
+
+    #[error("Reached the limit of max running searches")]
+    TooManySearches,
+
+    #[error("Search result offset is out of range")]
+    InvalidSearchOffset,
+
+    #[error("Metadata of torrent {hash} was not received in time")]
+    MetadataTimeout { hash: String },
+
+    #[error("Too many requests, retry after {retry_after:?}")]
+    RateLimited { retry_after: Option<Duration> },
+
+    #[error("`{endpoint}` is not available, it requires qBittorrent {since_version} or later")]
+    EndpointUnavailable {
+        endpoint: &'static str,
+        since_version: &'static str,
+    },
 }
 
 type Result<T, E = Error> = std::result::Result<T, E>;
@@ -1607,6 +3328,10 @@ mod test {
     };
 
     use tracing::info;
+    use wiremock::{
+        matchers::{body_string, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
 
     use super::*;
 
@@ -1639,6 +3364,37 @@ mod test {
         }
     }
 
+    /// Start a mock qBittorrent server and a client pointed at it. The client
+    /// uses a provided cookie so no login request is issued.
+    async fn mock() -> (MockServer, Qbit) {
+        let server = MockServer::start().await;
+        let api = Qbit::builder()
+            .endpoint(server.uri().as_str())
+            .cookie("SID=test")
+            .build();
+        (server, api)
+    }
+
+    /// Respond to `torrents/info` with the given torrents
+    fn torrent_list(torrents: impl IntoIterator<Item = serde_json::Value>) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(torrents.into_iter().collect::<Vec<_>>())
+    }
+
+    /// Respond to `torrents/info` with torrents that only have a hash
+    fn torrents(hashes: &[&str]) -> ResponseTemplate {
+        torrent_list(
+            hashes
+                .iter()
+                .map(|hash| serde_json::json!({ "hash": hash })),
+        )
+    }
+
+    /// Assert that input validation rejected every call before sending it
+    async fn assert_no_requests(server: &MockServer) {
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests.is_empty(), "Unexpected requests: {requests:?}");
+    }
+
     #[tokio::test]
     async fn test_login() {
         let client = prepare().await.unwrap();
@@ -1668,7 +3424,7 @@ mod test {
                 ]
                 .into(),
             },
-            ratio_limit: Some(1.0),
+            ratio_limit: Some(RatioLimit::Limited(1.0)),
             ..AddTorrentArg::default()
         };
         client.add_torrent(arg).await.unwrap();
@@ -1689,7 +3445,7 @@ mod test {
                         .to_vec(),
                 }]
             },
-            ratio_limit: Some(1.0),
+            ratio_limit: Some(RatioLimit::Limited(1.0)),
             ..AddTorrentArg::default()
         };
         client.add_torrent(arg).await.unwrap();
@@ -1704,4 +3460,1944 @@ mod test {
             .unwrap();
         print!("{:#?}", list);
     }
+
+    #[tokio::test]
+    async fn test_create_tags_rejects_comma() {
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/torrents/createTags"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let err = client
+            .create_tags(vec!["a,b".to_owned()])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidTagName { tag } if tag == "a,b"));
+
+        let err = client.delete_tags(vec![String::new()]).await.unwrap_err();
+        assert!(matches!(err, Error::InvalidTagName { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_create_tags() {
+        let (server, client) = mock().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v2/torrents/createTags"))
+            .and(body_string("tags=movie%2Ctv"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        client
+            .create_tags(vec!["movie".to_owned(), "tv".to_owned()])
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_connection_dropped() {
+        use tokio::{io::AsyncReadExt, net::TcpListener};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            // Read the request, then go away without responding
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 1024];
+            let _ = socket.read(&mut buf).await.unwrap();
+        });
+
+        let client = Qbit::builder()
+            .endpoint(format!("http://{addr}").as_str())
+            .cookie("SID=test")
+            .build();
+        client.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_invalid_response() {
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::TcpListener,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            // Answer with something that isn't HTTP
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 1024];
+            let _ = socket.read(&mut buf).await.unwrap();
+            socket.write_all(b"not http\r\n\r\n").await.unwrap();
+        });
+
+        let client = Qbit::builder()
+            .endpoint(format!("http://{addr}").as_str())
+            .cookie("SID=test")
+            .build();
+        assert!(matches!(client.shutdown().await, Err(Error::HttpError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_error_status() {
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/app/shutdown"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+
+        let err = client.shutdown().await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::UnknownHttpCode(StatusCode::INTERNAL_SERVER_ERROR)
+        ));
+    }
+
+    #[test]
+    fn test_debug_redacts_secrets() {
+        let api = Qbit::new("http://localhost:8080", Credential::new("admin", "hunter2"));
+        let debug = format!("{api:?}");
+        assert!(debug.contains("http://localhost:8080/"), "{debug}");
+        assert!(debug.contains("NotAuthenticated"), "{debug}");
+        assert!(!debug.contains("hunter2"), "{debug}");
+
+        api.state().add_cookie("SID=secret".to_owned());
+        let debug = format!("{api:?}");
+        assert!(debug.contains("Authenticated"), "{debug}");
+        assert!(!debug.contains("NotAuthenticated"), "{debug}");
+        assert!(!debug.contains("hunter2"), "{debug}");
+        assert!(!debug.contains("secret"), "{debug}");
+    }
+
+    #[tokio::test]
+    async fn test_last_known_id() {
+        use wiremock::matchers::{query_param, query_param_is_missing};
+
+        assert_eq!(normalize_last_known_id(None), None);
+        assert_eq!(normalize_last_known_id(Some(-1)), Some(-1));
+        assert_eq!(normalize_last_known_id(Some(-42)), Some(-1));
+        assert_eq!(normalize_last_known_id(Some(0)), Some(0));
+        assert_eq!(normalize_last_known_id(Some(42)), Some(42));
+        assert_eq!(
+            normalize_last_known_id(Some(i64::MAX)),
+            Some(i32::MAX.into())
+        );
+
+        let (server, client) = mock().await;
+        for (sent, expected) in [(-1, "-1"), (0, "0"), (i64::MAX, "2147483647")] {
+            Mock::given(path("/api/v2/log/main"))
+                .and(query_param("last_known_id", expected))
+                .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+                .expect(1)
+                .mount(&server)
+                .await;
+            client.get_logs(GetLogsArg::all_levels(sent)).await.unwrap();
+        }
+        Mock::given(path("/api/v2/log/peers"))
+            .and(query_param("last_known_id", "-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        client.get_peer_logs(-5).await.unwrap();
+        Mock::given(path("/api/v2/log/peers"))
+            .and(query_param_is_missing("last_known_id"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        client.get_peer_logs(None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_login() {
+        let server = MockServer::start().await;
+        Mock::given(path("/api/v2/auth/login"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("set-cookie", "SID=abc; HttpOnly")
+                    .set_delay(Duration::from_millis(100)),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/app/version"))
+            .and(wiremock::matchers::header("cookie", "SID=abc"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("v5.0.0"))
+            .expect(8)
+            .mount(&server)
+            .await;
+        let client = Arc::new(Qbit::new(
+            server.uri().as_str(),
+            Credential::new("admin", "adminadmin"),
+        ));
+
+        let tasks = (0..8)
+            .map(|_| {
+                let client = client.clone();
+                tokio::spawn(async move { client.get_version().await })
+            })
+            .collect::<Vec<_>>();
+        for task in tasks {
+            assert_eq!(task.await.unwrap().unwrap(), "v5.0.0");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_logged_in_skips_login_lock() {
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/app/version"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("v5.0.0"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        // A login in progress doesn't hold up requests that have a cookie
+        let _login = client.login_lock.lock().await;
+        let version = tokio::time::timeout(Duration::from_secs(5), client.get_version())
+            .await
+            .expect("Request waited for the login lock");
+        assert_eq!(version.unwrap(), "v5.0.0");
+    }
+
+    #[tokio::test]
+    async fn test_relogin_after_refresh() {
+        let server = MockServer::start().await;
+        Mock::given(path("/api/v2/auth/login"))
+            .respond_with(ResponseTemplate::new(200).insert_header("set-cookie", "SID=z; HttpOnly"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = Qbit::new(
+            server.uri().as_str(),
+            Credential::new("admin", "adminadmin"),
+        );
+        client.state().add_cookie("SID=y".to_owned());
+
+        // Rejected with a cookie that was already replaced by another task
+        assert_eq!(
+            client.relogin(Some("SID=x")).await.unwrap(),
+            LoginOutcome::AlreadyLoggedIn
+        );
+        assert_eq!(
+            client.relogin(Some("SID=y")).await.unwrap(),
+            LoginOutcome::Authenticated
+        );
+        assert_eq!(client.get_cookie().await.as_deref(), Some("SID=z"));
+    }
+
+    #[tokio::test]
+    async fn test_clear_share_limits() {
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/torrents/setShareLimits"))
+            .and(body_string(
+                "hashes=aaaa%7Cbbbb&ratioLimit=-1&seedingTimeLimit=-1&inactiveSeedingTimeLimit=-1",
+            ))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        client
+            .clear_share_limits(vec!["aaaa".to_owned(), "bbbb".to_owned()])
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_set_and_verify_share_limits() {
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/torrents/setShareLimits"))
+            .and(body_string(
+                "hashes=all&ratioLimit=1.5&seedingTimeLimit=-1&inactiveSeedingTimeLimit=-2",
+            ))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/torrents/info"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {
+                    "hash": "aaaa",
+                    "ratio_limit": 1.5,
+                    "seeding_time_limit": -1,
+                    "inactive_seeding_time_limit": -2,
+                },
+                {
+                    "hash": "bbbb",
+                    "ratio_limit": -2,
+                    "seeding_time_limit": -1,
+                    "inactive_seeding_time_limit": -2,
+                },
+            ])))
+            .mount(&server)
+            .await;
+
+        let report = client
+            .set_and_verify_share_limits(SetTorrentSharedLimitArg {
+                hashes: Hashes::All,
+                ratio_limit: Some(RatioLimit::Limited(1.5)),
+                seeding_time_limit: Some(SeedingTimeLimit::NoLimit),
+                inactive_seeding_time_limit: Some(SeedingTimeLimit::Global),
+            })
+            .await
+            .unwrap();
+        assert_eq!(report.applied, ["aaaa"]);
+        assert_eq!(report.not_applied, ["bbbb"]);
+    }
+
+    #[tokio::test]
+    async fn test_delete_torrents_reporting() {
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/torrents/info"))
+            .respond_with(torrents(&["aaaa", "bbbb"]))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/torrents/delete"))
+            .and(body_string("hashes=aaaa%7Cbbbb&deleteFiles=false"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/torrents/info"))
+            .respond_with(torrents(&["bbbb"]))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let removed = client
+            .delete_torrents_reporting(
+                vec!["aaaa".to_owned(), "bbbb".to_owned(), "cccc".to_owned()],
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(removed, ["aaaa"]);
+    }
+
+    #[cfg(feature = "stream")]
+    #[tokio::test]
+    async fn test_torrent_pages() {
+        use futures_util::TryStreamExt;
+        use wiremock::matchers::query_param;
+
+        let (server, client) = mock().await;
+        for (offset, hashes) in [("0", &["a", "b"][..]), ("2", &["c", "d"]), ("4", &["e"])] {
+            Mock::given(path("/api/v2/torrents/info"))
+                .and(query_param("limit", "2"))
+                .and(query_param("offset", offset))
+                .respond_with(torrents(hashes))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let pages = client
+            .torrent_pages(GetTorrentListArg::default(), 2)
+            .map_ok(|page| {
+                page.into_iter()
+                    .map(|torrent| torrent.hash.unwrap())
+                    .collect::<Vec<_>>()
+            })
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(pages, [vec!["a", "b"], vec!["c", "d"], vec!["e"]]);
+    }
+
+    #[tokio::test]
+    async fn test_set_first_last_piece_priority_verified() {
+        let (server, client) = mock().await;
+        let torrents =
+            |states: &[(&str, bool)]| {
+                torrent_list(states.iter().map(
+                    |(hash, prio)| serde_json::json!({ "hash": hash, "f_l_piece_prio": prio }),
+                ))
+            };
+        Mock::given(path("/api/v2/torrents/info"))
+            .respond_with(torrents(&[("a", false), ("b", true), ("c", false)]))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/torrents/toggleFirstLastPiecePrio"))
+            .and(body_string("hashes=a%7Cc"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        // `c` is already downloaded, so the toggle has no effect
+        Mock::given(path("/api/v2/torrents/info"))
+            .respond_with(torrents(&[("a", true), ("b", true), ("c", false)]))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let not_applied = client
+            .set_first_last_piece_priority_verified(
+                vec!["a".to_owned(), "b".to_owned(), "c".to_owned()],
+                true,
+            )
+            .await
+            .unwrap();
+        assert_eq!(not_applied, ["c"]);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited() {
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/app/version"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "5"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/app/version"))
+            .respond_with(ResponseTemplate::new(429))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+
+        let retry_after = Some(Duration::from_secs(5));
+        assert!(matches!(
+            client.get_version().await,
+            Err(Error::ApiError(ApiError::RateLimited { retry_after: r })) if r == retry_after
+        ));
+        assert!(matches!(
+            client.get_version().await,
+            Err(Error::ApiError(ApiError::RateLimited { retry_after: None }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_retries() {
+        let server = MockServer::start().await;
+        Mock::given(path("/api/v2/app/version"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/app/version"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("v5.0.0"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = Qbit::builder()
+            .endpoint(server.uri().as_str())
+            .cookie("SID=test")
+            .rate_limit_retries(2)
+            .build();
+
+        assert_eq!(client.get_version().await.unwrap(), "v5.0.0");
+    }
+
+    #[tokio::test]
+    async fn test_max_retry_after() {
+        let server = MockServer::start().await;
+        Mock::given(path("/api/v2/app/version"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "86400"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = Qbit::builder()
+            .endpoint(server.uri().as_str())
+            .cookie("SID=test")
+            .rate_limit_retries(2)
+            .build();
+
+        let retry_after = Some(Duration::from_secs(86400));
+        assert!(matches!(
+            client.get_version().await,
+            Err(Error::ApiError(ApiError::RateLimited { retry_after: r })) if r == retry_after
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_include_trackers() {
+        use wiremock::matchers::query_param;
+
+        let arg = GetTorrentListArg {
+            include_trackers: Some(true),
+            ..Default::default()
+        };
+
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/app/webapiVersion"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("2.11.3"))
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/torrents/info"))
+            .respond_with(ResponseTemplate::new(400))
+            .expect(0)
+            .mount(&server)
+            .await;
+        assert!(matches!(
+            client.get_torrent_list(arg.clone()).await,
+            Err(Error::ApiError(ApiError::EndpointUnavailable {
+                since_version: "5.1.0",
+                ..
+            }))
+        ));
+        drop(server);
+
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/app/webapiVersion"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("2.11.4"))
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/torrents/info"))
+            .and(query_param("includeTrackers", "true"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!([{
+                    "hash": "abc",
+                    "trackers": [],
+                }])),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let torrents = client.get_torrent_list(arg).await.unwrap();
+        assert_eq!(torrents[0].trackers, Some(vec![]));
+    }
+
+    #[tokio::test]
+    async fn test_endpoint_unavailable() {
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/app/webapiVersion"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("2.8.3"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/torrents/export"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        for _ in 0..2 {
+            assert!(matches!(
+                client.export_torrent("abc").await,
+                Err(Error::ApiError(ApiError::EndpointUnavailable {
+                    endpoint: "torrents/export",
+                    since_version: "4.5.0",
+                }))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_close() {
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/auth/logout"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        client.close().await.unwrap();
+
+        // Never logged in, so there's no session to end
+        Qbit::new(
+            server.uri().as_str(),
+            Credential::new("admin", "adminadmin"),
+        )
+        .close()
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_set_listen_port() {
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/app/setPreferences"))
+            .and(body_string("json=%7B%22listen_port%22%3A6881%7D"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        client.set_listen_port(6881).await.unwrap();
+        assert!(matches!(
+            client.set_listen_port(0).await,
+            Err(Error::InvalidPort(0))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_add_tracker_urls_scheme() {
+        let (server, client) = mock().await;
+        let urls = [
+            "udp://tracker.example.org:1337/announce",
+            "https://tracker.example.org/announce",
+            "ftp://tracker.example.org/announce",
+        ]
+        .map(|url| url.parse::<Url>().unwrap());
+
+        assert!(matches!(
+            client.add_tracker_urls("abc", urls).await,
+            Err(Error::UnsupportedTrackerScheme { url }) if url.scheme() == "ftp"
+        ));
+        assert_no_requests(&server).await;
+    }
+
+    #[tokio::test]
+    async fn test_default_save_path_cached() {
+        let (server, client) = mock().await;
+        // Shared like in an application, invalidating only needs `&self`
+        let client = Arc::new(client);
+        Mock::given(path("/api/v2/app/defaultSavePath"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("/downloads"))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/app/defaultSavePath"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("/data"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        for _ in 0..2 {
+            assert_eq!(
+                client.default_save_path_cached().await.unwrap(),
+                Path::new("/downloads")
+            );
+        }
+        client.invalidate_default_save_path();
+        assert_eq!(
+            client.default_save_path_cached().await.unwrap(),
+            Path::new("/data")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_file_priority_invalid_id() {
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/torrents/filePrio"))
+            .respond_with(ResponseTemplate::new(400))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        assert!(matches!(
+            client
+                .set_file_priority("abc", vec![42], Priority::High)
+                .await,
+            Err(Error::ApiError(ApiError::InvalidFilePriorityOrId))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_set_torrent_name_conflict() {
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/torrents/rename"))
+            .respond_with(ResponseTemplate::new(409))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        assert!(matches!(
+            client
+                .set_torrent_name("abc", NonEmptyStr::new("a/b").unwrap())
+                .await,
+            Err(Error::ApiError(ApiError::InvalidTorrentName))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_version_trimmed() {
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/app/version"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("v4.6.2\n"))
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/app/webapiVersion"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("2.9.3\n"))
+            .mount(&server)
+            .await;
+
+        assert_eq!(client.get_version().await.unwrap(), "v4.6.2");
+        assert_eq!(client.get_webapi_version().await.unwrap(), "2.9.3");
+    }
+
+    #[tokio::test]
+    async fn test_no_auth() {
+        let server = MockServer::start().await;
+        Mock::given(path("/api/v2/auth/login"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/app/version"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("v5.0.0"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = Qbit::builder()
+            .endpoint(server.uri().as_str())
+            .no_auth()
+            .build();
+
+        assert_eq!(client.get_version().await.unwrap(), "v5.0.0");
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests
+            .iter()
+            .all(|req| !req.headers.contains_key(header::COOKIE)));
+    }
+
+    #[tokio::test]
+    async fn test_build_and_verify() {
+        use wiremock::matchers::header;
+
+        let server = MockServer::start().await;
+        Mock::given(path("/api/v2/app/version"))
+            .and(header("cookie", "SID=good"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("v5.0.0"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/app/version"))
+            .respond_with(ResponseTemplate::new(403).set_body_string("Forbidden"))
+            .mount(&server)
+            .await;
+        let endpoint: Url = server.uri().parse().unwrap();
+        let build = |cookie: &str| {
+            Qbit::builder()
+                .endpoint(endpoint.clone())
+                .cookie(cookie)
+                .build_and_verify()
+        };
+
+        assert!(build("SID=good").await.is_ok());
+        assert!(matches!(
+            build("SID=expired").await,
+            Err(Error::ApiError(ApiError::NotLoggedIn))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_add_torrent_checked() {
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/torrents/add"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/torrents/info"))
+            .respond_with(torrents(&["a"]))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/torrents/info"))
+            .respond_with(torrents(&["a", "b"]))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        let magnet = "c12fe1c06bba254a9dc9f519b335aa7c1367a88a";
+        Mock::given(path("/api/v2/torrents/info"))
+            .respond_with(torrents(&["a", "b", magnet]))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let arg = |url: &str| AddTorrentArg {
+            source: TorrentSource::Urls {
+                urls: vec![url.parse().unwrap()].into(),
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            client
+                .add_torrent_checked(arg("https://example.com/b.torrent"))
+                .await
+                .unwrap(),
+            AddOutcome::Added(vec!["b".to_owned()])
+        );
+        assert_eq!(
+            client
+                .add_torrent_checked(arg(&format!("magnet:?xt=urn:btih:{magnet}")))
+                .await
+                .unwrap(),
+            AddOutcome::AlreadyPresent
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_hashes_except() {
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/torrents/info"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "hash": "a" },
+                { "hash": "b" },
+                { "hash": "c" },
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let hashes = client
+            .resolve_hashes_except(Hashes::All, &["b".to_owned(), "d".to_owned()])
+            .await
+            .unwrap();
+        assert_eq!(hashes, Hashes::from(vec!["a".to_owned(), "c".to_owned()]));
+    }
+
+    #[tokio::test]
+    async fn test_reannounce_with_min_interval() {
+        use wiremock::matchers::query_param;
+
+        let (server, client) = mock().await;
+        for (hash, reannounce) in [("a", 30), ("b", 1800), ("c", 0)] {
+            Mock::given(path("/api/v2/torrents/properties"))
+                .and(query_param("hash", hash))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .set_body_json(serde_json::json!({ "reannounce": reannounce })),
+                )
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        Mock::given(path("/api/v2/torrents/reannounce"))
+            .and(body_string("hashes=a%7Cc"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let reannounced = client
+            .reannounce_with_min_interval(
+                vec!["a".to_owned(), "b".to_owned(), "c".to_owned()],
+                Duration::from_secs(60),
+            )
+            .await
+            .unwrap();
+        assert_eq!(reannounced, ["a", "c"]);
+    }
+
+    #[cfg(feature = "stream")]
+    #[tokio::test]
+    async fn test_search_stream() {
+        use futures_util::TryStreamExt;
+        use wiremock::matchers::query_param;
+
+        let result = |name: &str| {
+            serde_json::json!({
+                "descrLink": "http://example.com/desc",
+                "fileName": name,
+                "fileSize": 1024,
+                "fileUrl": "magnet:?xt=urn:btih:0000",
+                "nbLeechers": 1,
+                "nbSeeders": 2,
+                "siteUrl": "http://example.com",
+            })
+        };
+
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/search/start"))
+            .and(body_string("pattern=ubuntu&plugins=all&category=software"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": 7 })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/search/results"))
+            .and(query_param("offset", "0"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "results": [result("a"), result("b")],
+                "status": "Running",
+                "total": 2,
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/search/results"))
+            .and(query_param("offset", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "results": [result("c")],
+                "status": "Stopped",
+                "total": 3,
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/search/delete"))
+            .and(body_string("id=7"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let names = client
+            .search_stream(
+                "ubuntu",
+                vec!["all".to_owned()],
+                SearchCategory::Software,
+                std::time::Duration::from_millis(10),
+            )
+            .map_ok(|r| r.file_name)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(names, ["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn test_login_outcome() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v2/auth/login"))
+            .and(body_string("username=admin&password=adminadmin"))
+            .respond_with(
+                ResponseTemplate::new(200).insert_header("set-cookie", "SID=abc; HttpOnly; path=/"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Qbit::new(
+            server.uri().as_str(),
+            Credential::new("admin", "adminadmin"),
+        );
+        assert_eq!(
+            client.login_with_outcome(false).await.unwrap(),
+            LoginOutcome::Authenticated
+        );
+        assert_eq!(
+            client.login_with_outcome(false).await.unwrap(),
+            LoginOutcome::AlreadyLoggedIn
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_torrent_not_resent() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v2/auth/login"))
+            .respond_with(
+                ResponseTemplate::new(200).insert_header("set-cookie", "SID=abc; HttpOnly; path=/"),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v2/torrents/add"))
+            .respond_with(ResponseTemplate::new(403).set_body_string("Forbidden"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Qbit::new(
+            server.uri().as_str(),
+            Credential::new("admin", "adminadmin"),
+        );
+        let arg = AddTorrentArg {
+            source: TorrentSource::Urls {
+                urls: vec!["magnet:?xt=urn:btih:abc".parse().unwrap()].into(),
+            },
+            ..Default::default()
+        };
+        assert!(matches!(
+            client.add_torrent(arg).await,
+            Err(Error::ApiError(ApiError::NotLoggedIn))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_set_torrent_location_empty_path() {
+        let (server, client) = mock().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v2/torrents/setLocation"))
+            .and(body_string("hashes=abc&location=%2Fdownloads"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        assert!(matches!(
+            client
+                .set_torrent_location(vec!["abc".to_owned()], "")
+                .await,
+            Err(Error::EmptyPath)
+        ));
+        client
+            .set_torrent_location(vec!["abc".to_owned()], "/downloads")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_clear_torrent_category() {
+        let (server, client) = mock().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v2/torrents/setCategory"))
+            .and(body_string("hashes=abc&category="))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        client
+            .clear_torrent_category(vec!["abc".to_owned()])
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_proxy() {
+        let proxy = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v2/app/version"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("v4.6.2"))
+            .expect(1)
+            .mount(&proxy)
+            .await;
+
+        // The endpoint can't be resolved, so the request only succeeds through
+        // the proxy
+        let client = Qbit::builder()
+            .endpoint("http://qbittorrent.invalid")
+            .cookie("SID=test")
+            .proxy(reqwest::Proxy::http(proxy.uri()).unwrap())
+            .build();
+        assert_eq!(client.get_version().await.unwrap(), "v4.6.2");
+    }
+
+    #[test]
+    fn test_try_new_invalid_endpoint() {
+        let res = Qbit::try_new("not a url", Credential::new("admin", "adminadmin"));
+        assert!(matches!(res, Err(Error::InvalidEndpoint(_))));
+
+        Qbit::try_new(
+            "http://localhost:8080",
+            Credential::new("admin", "adminadmin"),
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_forwarded_for() {
+        use wiremock::matchers::header;
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v2/app/version"))
+            .and(header("x-forwarded-for", "203.0.113.7"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("v4.6.2"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Qbit::builder()
+            .endpoint(server.uri().as_str())
+            .cookie("SID=test")
+            .forwarded_for([203, 0, 113, 7].into())
+            .build();
+        assert_eq!(client.get_version().await.unwrap(), "v4.6.2");
+    }
+
+    #[tokio::test]
+    async fn test_cache_metadata() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v2/torrents/tags"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(["a"]))
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v2/torrents/createTags"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Qbit::builder()
+            .endpoint(server.uri().as_str())
+            .cookie("SID=test")
+            .cache_metadata(std::time::Duration::from_secs(60))
+            .build();
+        assert_eq!(client.get_all_tags().await.unwrap(), ["a"]);
+        assert_eq!(client.get_all_tags().await.unwrap(), ["a"]);
+
+        client.create_tags(vec!["b".to_owned()]).await.unwrap();
+        assert_eq!(client.get_all_tags().await.unwrap(), ["a"]);
+    }
+
+    #[tokio::test]
+    async fn test_cache_edit_category() {
+        let server = MockServer::start().await;
+        Mock::given(path("/api/v2/torrents/categories"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "movies": { "name": "movies", "savePath": "/movies" },
+            })))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/torrents/categories"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "movies": { "name": "movies", "savePath": "/films" },
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v2/torrents/editCategory"))
+            .and(body_string("category=movies&savePath=%2Ffilms"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Qbit::builder()
+            .endpoint(server.uri().as_str())
+            .cookie("SID=test")
+            .cache_metadata(std::time::Duration::from_secs(60))
+            .build();
+        let save_path =
+            |categories: HashMap<String, Category>| categories["movies"].save_path.clone();
+        assert_eq!(
+            save_path(client.get_categories().await.unwrap()),
+            Path::new("/movies")
+        );
+
+        client
+            .edit_category(NonEmptyStr::new("movies").unwrap(), "/films")
+            .await
+            .unwrap();
+        assert_eq!(
+            save_path(client.get_categories().await.unwrap()),
+            Path::new("/films")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_peer_countries_enabled() {
+        for (body, enabled) in [
+            (r#"{"resolve_peer_countries":true}"#, true),
+            (r#"{"resolve_peer_countries":false}"#, false),
+            ("{}", false),
+        ] {
+            let (server, client) = mock().await;
+            Mock::given(path("/api/v2/app/preferences"))
+                .respond_with(ResponseTemplate::new(200).set_body_string(body))
+                .expect(1)
+                .mount(&server)
+                .await;
+
+            assert_eq!(client.peer_countries_enabled().await.unwrap(), enabled);
+        }
+    }
+
+    #[test]
+    fn test_api_base() {
+        let api = Qbit::builder()
+            .endpoint("http://localhost:8080/qbt/")
+            .cookie("SID=test")
+            .build();
+        assert_eq!(
+            api.url("app/version").as_str(),
+            "http://localhost:8080/qbt/api/v2/app/version"
+        );
+
+        let api = Qbit::builder()
+            .endpoint("http://localhost:8080/qbt/")
+            .cookie("SID=test")
+            .api_base("api/v3")
+            .build();
+        assert_eq!(
+            api.url("app/version").as_str(),
+            "http://localhost:8080/qbt/api/v3/app/version"
+        );
+
+        assert!(matches!(
+            Qbit::builder()
+                .endpoint("http://localhost:8080")
+                .cookie("SID=test")
+                .api_base("http://[::1")
+                .try_build(),
+            Err(Error::InvalidEndpoint(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_empty_hashes() {
+        let (server, client) = mock().await;
+
+        assert!(matches!(
+            client.delete_torrents(Vec::<String>::new(), true).await,
+            Err(Error::EmptyInput { param: "hashes" })
+        ));
+        assert!(matches!(
+            client
+                .pause_torrents(Hashes::from(Vec::<String>::new()))
+                .await,
+            Err(Error::EmptyInput { param: "hashes" })
+        ));
+        assert_no_requests(&server).await;
+    }
+
+    #[tokio::test]
+    async fn test_add_torrent_failed() {
+        let (server, client) = mock().await;
+        let add = |url: &str| AddTorrentArg {
+            source: TorrentSource::Urls {
+                urls: vec![url.parse().unwrap()].into(),
+            },
+            ..Default::default()
+        };
+        let add_result = |template: ResponseTemplate| {
+            let server = &server;
+            let client = &client;
+            async move {
+                server.reset().await;
+                Mock::given(path("/api/v2/torrents/add"))
+                    .respond_with(template)
+                    .mount(server)
+                    .await;
+                client
+                    .add_torrent(add("http://example.com/a.torrent"))
+                    .await
+            }
+        };
+
+        let reason = |res: Result<()>| match res {
+            Err(Error::ApiError(ApiError::TorrentAddFailed { reason })) => reason,
+            res => panic!("Unexpected result: {res:?}"),
+        };
+        assert_eq!(
+            reason(add_result(ResponseTemplate::new(415)).await),
+            "Torrent file is not valid"
+        );
+        assert_eq!(
+            reason(
+                add_result(
+                    ResponseTemplate::new(415).set_body_string("Torrent file is not valid.")
+                )
+                .await
+            ),
+            "Torrent file is not valid."
+        );
+        assert_eq!(
+            reason(add_result(ResponseTemplate::new(200).set_body_string("Fails.")).await),
+            "Fails."
+        );
+        assert_eq!(
+            reason(add_result(ResponseTemplate::new(409).set_body_string("Fails.")).await),
+            "Fails."
+        );
+
+        add_result(ResponseTemplate::new(200).set_body_string("Ok."))
+            .await
+            .unwrap();
+        assert!(matches!(
+            add_result(ResponseTemplate::new(400)).await,
+            Err(Error::UnknownHttpCode(StatusCode::BAD_REQUEST))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_add_torrent_empty_source() {
+        let (server, client) = mock().await;
+
+        for source in [
+            TorrentSource::default(),
+            TorrentSource::TorrentFiles { torrents: vec![] },
+        ] {
+            let arg = AddTorrentArg {
+                source,
+                ..AddTorrentArg::default()
+            };
+            assert!(matches!(
+                client.add_torrent(&arg).await,
+                Err(Error::EmptyInput { param: "source" })
+            ));
+        }
+        assert_no_requests(&server).await;
+    }
+
+    #[tokio::test]
+    async fn test_error_classification() {
+        let not_found = Error::ApiError(ApiError::TorrentNotFound);
+        assert!(not_found.is_not_found());
+        assert!(!not_found.is_auth_error());
+        assert!(!not_found.is_retryable());
+        assert!(Error::UnknownHttpCode(StatusCode::NOT_FOUND).is_not_found());
+
+        let auth = Error::ApiError(ApiError::NotLoggedIn);
+        assert!(auth.is_auth_error());
+        assert!(auth.is_retryable());
+        assert!(!auth.is_not_found());
+        assert!(Error::ApiError(ApiError::IpBanned).is_auth_error());
+        assert!(!Error::ApiError(ApiError::IpBanned).is_retryable());
+
+        // Nothing listens on port 1
+        let refused = Qbit::builder()
+            .endpoint("http://127.0.0.1:1")
+            .cookie("SID=test")
+            .build()
+            .get_version()
+            .await
+            .unwrap_err();
+        assert!(refused.is_transport());
+        assert!(refused.is_retryable());
+        assert!(!refused.is_not_found());
+
+        let builder = reqwest::Client::new()
+            .get("http://localhost")
+            .header("bad\nheader", "")
+            .build()
+            .map(|_| ())
+            .map_err(Error::from)
+            .unwrap_err();
+        assert!(!builder.is_transport());
+        assert!(!builder.is_retryable());
+
+        let rate_limited = Error::ApiError(ApiError::RateLimited { retry_after: None });
+        assert!(rate_limited.is_retryable());
+        assert!(!rate_limited.is_transport());
+        assert!(Error::UnknownHttpCode(StatusCode::BAD_GATEWAY).is_retryable());
+        assert!(!Error::EmptyPath.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_share_limits_empty() {
+        let (server, client) = mock().await;
+        let arg = SetTorrentSharedLimitArg {
+            hashes: Hashes::All,
+            ratio_limit: None,
+            seeding_time_limit: None,
+            inactive_seeding_time_limit: None,
+        };
+
+        assert!(matches!(
+            client.set_torrent_shared_limit(&arg).await,
+            Err(Error::EmptyInput { param: "limits" })
+        ));
+        assert!(matches!(
+            client.set_and_verify_share_limits(&arg).await,
+            Err(Error::EmptyInput { param: "limits" })
+        ));
+        assert_no_requests(&server).await;
+    }
+
+    #[tokio::test]
+    async fn test_empty_input() {
+        let (server, client) = mock().await;
+
+        assert!(matches!(
+            client
+                .set_file_priority("abc", Vec::<i64>::new(), Priority::High)
+                .await,
+            Err(Error::EmptyInput { param: "indexes" })
+        ));
+        assert!(matches!(
+            client.remove_trackers("abc", Vec::<Url>::new()).await,
+            Err(Error::EmptyInput { param: "urls" })
+        ));
+        assert_no_requests(&server).await;
+    }
+
+    #[tokio::test]
+    async fn test_forbidden_not_session() {
+        let (server, client) = mock().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v2/torrents/setLocation"))
+            .respond_with(ResponseTemplate::new(403).set_body_string("Cannot write to directory"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        assert!(matches!(
+            client
+                .set_torrent_location(vec!["abc".to_owned()], "/readonly")
+                .await,
+            Err(Error::ApiError(ApiError::NoWriteAccess))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_request_raw() {
+        let (server, client) = mock().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v2/torrents/properties"))
+            .and(wiremock::matchers::query_param("hash", "abc"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let endpoint = String::from("torrents/") + "properties";
+        let res = client
+            .request_raw(Method::GET, &endpoint, Some(&[("hash", "abc")]))
+            .await
+            .unwrap();
+        assert!(res.status().is_success());
+
+        assert!(matches!(
+            client
+                .request_raw(Method::PUT, endpoint, Some(&[("hash", "abc")]))
+                .await,
+            Err(Error::UnsupportedMethod(Method::PUT))
+        ));
+        assert!(matches!(
+            client.request_raw(Method::GET, "http://[::1", NONE).await,
+            Err(Error::InvalidEndpoint(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rebuilt_response_url() {
+        let (server, client) = mock().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v2/torrents/setLocation"))
+            .respond_with(ResponseTemplate::new(403).set_body_string("Cannot write to directory"))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v2/torrents/addTags"))
+            .respond_with(
+                ResponseTemplate::new(400)
+                    .set_body_json(serde_json::json!({"error": "Invalid tag"})),
+            )
+            .mount(&server)
+            .await;
+
+        // Both responses are rebuilt after their body is read
+        for (path, message) in [
+            ("torrents/setLocation", None),
+            ("torrents/addTags", Some("Invalid tag")),
+        ] {
+            let res = client.request_raw(Method::POST, path, NONE).await.unwrap();
+            assert_eq!(res.error_message(), message);
+            assert_eq!(
+                res.url().as_str(),
+                format!("{}/api/v2/{path}", server.uri())
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrent_requests() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        /// Records the most requests in flight at once
+        #[derive(Default)]
+        struct PeakTransport {
+            client: Client,
+            in_flight: AtomicUsize,
+            peak: AtomicUsize,
+        }
+
+        impl Transport for PeakTransport {
+            fn execute(&self, request: reqwest::Request) -> transport::TransportFuture<'_> {
+                Box::pin(async move {
+                    let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    self.peak.fetch_max(now, Ordering::SeqCst);
+                    let res = self.client.execute(request).await;
+                    self.in_flight.fetch_sub(1, Ordering::SeqCst);
+                    Ok(res?)
+                })
+            }
+        }
+
+        let server = MockServer::start().await;
+        Mock::given(path("/api/v2/app/version"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string("v4.6.2")
+                    .set_delay(std::time::Duration::from_millis(50)),
+            )
+            .expect(5)
+            .mount(&server)
+            .await;
+
+        let transport = Arc::new(PeakTransport::default());
+        let client = Qbit::builder()
+            .endpoint(server.uri().as_str())
+            .cookie("SID=test")
+            .max_concurrent_requests(2)
+            .transport(transport.clone())
+            .build();
+
+        let results = tokio::join!(
+            client.get_version(),
+            client.get_version(),
+            client.get_version(),
+            client.get_version(),
+            client.get_version()
+        );
+        for result in [results.0, results.1, results.2, results.3, results.4] {
+            result.unwrap();
+        }
+        assert_eq!(transport.peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_get_torrent_contents_index() {
+        let content = |name: &str, index: Option<u64>| {
+            let mut content = serde_json::json!({
+                "name": name,
+                "size": 1024,
+                "progress": 0.5,
+                "priority": 1,
+            });
+            if let Some(index) = index {
+                content["index"] = index.into();
+            }
+            content
+        };
+
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/torrents/files"))
+            .and(wiremock::matchers::query_param("hash", "filtered"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                content("c.mkv", Some(2)),
+                content("f.mkv", Some(5)),
+            ])))
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/torrents/files"))
+            .and(wiremock::matchers::query_param("hash", "legacy"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                content("a.mkv", None),
+                content("b.mkv", None),
+            ])))
+            .mount(&server)
+            .await;
+
+        let indexes = |contents: Vec<TorrentContent>| {
+            contents.into_iter().map(|c| c.index).collect::<Vec<_>>()
+        };
+        let filtered = client
+            .get_torrent_contents("filtered", Sep::from(vec!["2".to_owned(), "5".to_owned()]))
+            .await
+            .unwrap();
+        assert_eq!(indexes(filtered), [2, 5]);
+
+        let legacy = client.get_torrent_contents("legacy", None).await.unwrap();
+        assert_eq!(indexes(legacy), [0, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_get_torrent_detail() {
+        use wiremock::matchers::query_param;
+
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/torrents/info"))
+            .and(query_param("hashes", "abc"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!([{ "hash": "abc", "name": "a" }])),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/torrents/properties"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/torrents/trackers"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!([{
+                    "url": "http://a.example/announce",
+                    "status": 2,
+                    "tier": 0,
+                    "num_peers": 1,
+                    "num_seeds": 1,
+                    "num_leeches": 0,
+                    "num_downloaded": 3,
+                    "msg": "",
+                }])),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/torrents/files"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!([{
+                    "index": 0,
+                    "name": "a.mkv",
+                    "size": 1024,
+                    "progress": 1.0,
+                    "priority": 1,
+                }])),
+            )
+            .mount(&server)
+            .await;
+
+        let detail = client.get_torrent_detail("abc").await.unwrap();
+        assert_eq!(detail.torrent.name.as_deref(), Some("a"));
+        assert_eq!(detail.properties, None);
+        assert_eq!(detail.trackers.unwrap().len(), 1);
+        assert_eq!(detail.contents.unwrap()[0].name, "a.mkv");
+
+        // Not in the list at all
+        Mock::given(path("/api/v2/torrents/info"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .mount(&server)
+            .await;
+        assert!(matches!(
+            client.get_torrent_detail("missing").await,
+            Err(Error::ApiError(ApiError::TorrentNotFound))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_torrent_page() {
+        use wiremock::matchers::{query_param, query_param_is_missing};
+
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/torrents/info"))
+            .and(query_param("filter", "completed"))
+            .and(query_param("sort", "name"))
+            .and(query_param("limit", "2"))
+            .and(query_param("offset", "2"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!([{ "hash": "c" }, { "hash": "d" }])),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/torrents/info"))
+            .and(query_param("filter", "completed"))
+            .and(query_param_is_missing("limit"))
+            .and(query_param_is_missing("offset"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "hash": "a" },
+                { "hash": "b" },
+                { "hash": "c" },
+                { "hash": "d" },
+                { "hash": "e" },
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let arg = GetTorrentListArg {
+            filter: Some(TorrentFilter::Completed),
+            sort: Some("name".to_owned()),
+            limit: Some(100),
+            ..Default::default()
+        };
+        let page = client
+            .get_torrent_page(arg.clone(), 1, 2, true)
+            .await
+            .unwrap();
+        assert_eq!(page.total, Some(5));
+        assert_eq!(
+            page.items
+                .iter()
+                .map(|t| t.hash.as_deref().unwrap())
+                .collect::<Vec<_>>(),
+            ["c", "d"]
+        );
+
+        // Not counted unless asked for
+        server.verify().await;
+        server.reset().await;
+        Mock::given(path("/api/v2/torrents/info"))
+            .and(query_param("offset", "2"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!([{ "hash": "c" }, { "hash": "d" }])),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let page = client
+            .get_torrent_page(arg.clone(), 1, 2, false)
+            .await
+            .unwrap();
+        assert_eq!(page.total, None);
+
+        // Known from the last page without counting
+        Mock::given(path("/api/v2/torrents/info"))
+            .and(query_param("offset", "4"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!([{ "hash": "e" }])),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let page = client.get_torrent_page(arg, 2, 2, true).await.unwrap();
+        assert_eq!(page.total, Some(5));
+    }
+
+    #[tokio::test]
+    async fn test_rename_files() {
+        use wiremock::matchers::body_string_contains;
+
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/torrents/renameFile"))
+            .and(body_string_contains("oldPath=b.mkv"))
+            .respond_with(ResponseTemplate::new(409))
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/torrents/renameFile"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let results = client
+            .rename_files(
+                "abc",
+                vec![
+                    ("a.mkv".into(), "S01E01.mkv".into()),
+                    ("b.mkv".into(), "S01E01.mkv".into()),
+                    ("c.mkv".into(), "S01E03.mkv".into()),
+                ],
+            )
+            .await
+            .unwrap();
+
+        let paths = results
+            .iter()
+            .map(|(p, _)| p.to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["a.mkv", "b.mkv", "c.mkv"]);
+        assert!(results[0].1.is_ok());
+        assert!(matches!(
+            results[1].1,
+            Err(Error::ApiError(ApiError::InvalidPath))
+        ));
+        assert!(results[2].1.is_ok());
+
+        assert!(matches!(
+            client.rename_files("abc", vec![]).await,
+            Err(Error::EmptyInput { param: "renames" })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_dashboard_snapshot() {
+        let (server, client) = mock().await;
+        let respond = |endpoint: &str, body: ResponseTemplate| {
+            Mock::given(path(format!("/api/v2/{endpoint}")))
+                .respond_with(body)
+                .expect(1)
+                .mount(&server)
+        };
+        respond(
+            "transfer/info",
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "connection_status": "connected",
+                "dht_nodes": 386,
+                "dl_info_data": 681521119,
+                "dl_info_speed": 1024,
+                "dl_rate_limit": 0,
+                "up_info_data": 10747904,
+                "up_info_speed": 512,
+                "up_rate_limit": 1048576,
+            })),
+        )
+        .await;
+        respond(
+            "transfer/speedLimitsMode",
+            ResponseTemplate::new(200).set_body_string("1"),
+        )
+        .await;
+        respond(
+            "transfer/downloadLimit",
+            ResponseTemplate::new(200).set_body_string("0"),
+        )
+        .await;
+        respond(
+            "transfer/uploadLimit",
+            ResponseTemplate::new(200).set_body_string("1048576"),
+        )
+        .await;
+        respond(
+            "torrents/info",
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!([{ "hash": "abc" }, { "hash": "def" }])),
+        )
+        .await;
+
+        let snapshot = client.get_dashboard_snapshot().await.unwrap();
+        assert_eq!(snapshot.transfer.dl_info_speed, 1024);
+        assert!(snapshot.alt_speed_limits);
+        assert_eq!(snapshot.download_limit, 0);
+        assert_eq!(snapshot.upload_limit, 1048576);
+        assert_eq!(snapshot.torrent_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_logout_during_requests() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        use wiremock::{matchers::header, Request};
+
+        let server = MockServer::start().await;
+        let client = Qbit::builder()
+            .endpoint(server.uri().as_str())
+            .credential(Credential::new("admin", "adminadmin"))
+            .build();
+        for cookie in ["SID=old", "SID=new"] {
+            Mock::given(path("/api/v2/auth/login"))
+                .respond_with(ResponseTemplate::new(200).insert_header("set-cookie", cookie))
+                .up_to_n_times(1)
+                .mount(&server)
+                .await;
+        }
+        let logged_out = Arc::new(AtomicBool::new(false));
+        Mock::given(path("/api/v2/auth/logout"))
+            .respond_with({
+                let logged_out = logged_out.clone();
+                move |_: &Request| {
+                    logged_out.store(true, Ordering::SeqCst);
+                    ResponseTemplate::new(200)
+                }
+            })
+            .mount(&server)
+            .await;
+        // The old session is rejected once it's logged out
+        Mock::given(path("/api/v2/app/version"))
+            .and(header("cookie", "SID=old"))
+            .respond_with(move |_: &Request| {
+                if logged_out.load(Ordering::SeqCst) {
+                    ResponseTemplate::new(403)
+                } else {
+                    ResponseTemplate::new(200).set_body_string("v4.6.0")
+                }
+            })
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/app/version"))
+            .and(header("cookie", "SID=new"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("v4.6.0"))
+            .mount(&server)
+            .await;
+
+        client.login(false).await.unwrap();
+        let (a, logout, b, c) = tokio::join!(
+            client.get_version(),
+            client.logout(),
+            client.get_version(),
+            client.get_version(),
+        );
+        logout.unwrap();
+        for version in [a, b, c] {
+            assert_eq!(version.unwrap(), "v4.6.0");
+        }
+
+        // Either forgotten by the logout, or replaced by logging in again once
+        assert_ne!(client.get_cookie().await.as_deref(), Some("SID=old"));
+        let logins = server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|req| req.url.path() == "/api/v2/auth/login")
+            .count();
+        assert!(logins <= 2, "Logged in {logins} times");
+    }
+
+    #[tokio::test]
+    async fn test_add_torrent_selective() {
+        // No stop condition, the torrent is added running
+        add_torrent_selective("2.8.2", None).await;
+        add_torrent_selective("2.9.3", Some("/api/v2/torrents/resume")).await;
+        // qBittorrent 5
+        add_torrent_selective("2.11.2", Some("/api/v2/torrents/start")).await;
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_metadata_timeout() {
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/torrents/files"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .mount(&server)
+            .await;
+
+        assert!(matches!(
+            client
+                .wait_for_metadata("abc", Duration::from_millis(50))
+                .await,
+            Err(Error::ApiError(ApiError::MetadataTimeout { hash })) if hash == "abc"
+        ));
+    }
+
+    async fn add_torrent_selective(webapi_version: &str, start_path: Option<&str>) {
+        use wiremock::matchers::{body_string_contains, method};
+
+        let hash = "c12fe1c06bba254a9dc9f519b335aa7c1367a88a";
+        let file = |index: u64| {
+            serde_json::json!({
+                "index": index,
+                "name": format!("{index}.mkv"),
+                "size": 1024,
+                "progress": 0.0,
+                "priority": 1,
+            })
+        };
+
+        let (server, client) = mock().await;
+        Mock::given(path("/api/v2/torrents/info"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/app/webapiVersion"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(webapi_version))
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/torrents/add"))
+            .and(body_string_contains("stopCondition=MetadataReceived"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(u64::from(start_path.is_some()))
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/torrents/add"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(u64::from(start_path.is_none()))
+            .mount(&server)
+            .await;
+        // Metadata is still being downloaded on the first poll
+        Mock::given(path("/api/v2/torrents/files"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/torrents/files"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                file(0),
+                file(1),
+                file(2)
+            ])))
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v2/torrents/filePrio"))
+            .and(method("POST"))
+            .and(body_string_contains("id=0%7C2"))
+            .and(body_string_contains("priority=0"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        if let Some(start_path) = start_path {
+            Mock::given(path(start_path))
+                .and(body_string(format!("hashes={hash}")))
+                .respond_with(ResponseTemplate::new(200))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let arg = AddTorrentArg {
+            source: TorrentSource::Urls {
+                urls: vec![format!("magnet:?xt=urn:btih:{hash}").parse().unwrap()].into(),
+            },
+            ..AddTorrentArg::default()
+        };
+        let outcome = client
+            .add_torrent_selective(&arg, Some(vec![1]))
+            .await
+            .unwrap();
+        assert_eq!(outcome, AddOutcome::Added(vec![hash.to_owned()]));
+    }
 }
@@ -1,11 +1,13 @@
 use std::{collections::HashMap, path::PathBuf};
 
+#[cfg(feature = "time")]
+use chrono::NaiveTime;
 use serde::{de::Visitor, Deserialize, Deserializer, Serialize, Serializer};
 use serde_with::skip_serializing_none;
 
 use crate::model::IntOrStr;
 
-#[derive(Debug, Clone, serde::Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
 pub struct BuildInfo {
     /// QT version
     qt: String,
@@ -19,13 +21,15 @@ pub struct BuildInfo {
     bitness: i8,
 }
 
+// `skip_serializing_none` must come before the derive to take effect, so that
+// unset preferences are left out instead of being sent as `null`
+#[skip_serializing_none]
 #[cfg_attr(feature = "builder", derive(typed_builder::TypedBuilder))]
 #[cfg_attr(
     feature = "builder",
     builder(field_defaults(default, setter(strip_option)))
 )]
 #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, PartialEq)]
-#[skip_serializing_none]
 pub struct Preferences {
     /// Currently selected language (e.g. en_GB for English)
     pub locale: Option<String>,
@@ -175,7 +179,8 @@ pub struct Preferences {
     /// built against libtorrent version 0.16.X and higher
     pub anonymous_mode: Option<bool>,
     /// In old versions (before 4.6.1 or early), this returns an interger. In
-    /// newer versions, this returns a string.
+    /// newer versions, this returns a string. See [`Preferences::proxy_config`]
+    /// for a typed view of the proxy preferences.
     pub proxy_type: Option<IntOrStr>,
     /// Proxy IP address or domain name
     pub proxy_ip: Option<String>,
@@ -255,7 +260,7 @@ pub struct Preferences {
     pub web_ui_https_cert_path: Option<String>,
     /// True if server DNS should be updated dynamically
     pub dyndns_enabled: Option<bool>,
-    /// See list of possible values here below
+    /// DDNS service to update, see [`Preferences::dyndns_service_kind`]
     pub dyndns_service: Option<i64>,
     /// Username for DDNS service
     pub dyndns_username: Option<String>,
@@ -304,6 +309,37 @@ pub struct Preferences {
     pub current_interface_address: Option<String>,
     /// Network Interface used
     pub current_network_interface: Option<String>,
+    /// For API ≥ v2.8.2: Name of the network interface used
+    pub current_interface_name: Option<String>,
+    /// For qBittorrent ≥ v4.4: Physical memory (RAM) usage limit in MiB.
+    /// Windows only
+    pub memory_working_set_limit: Option<i64>,
+    /// For qBittorrent ≥ v4.4: Number of threads used to hash pieces
+    pub hashing_threads: Option<i64>,
+    /// For qBittorrent ≥ v4.4: Maximum bytes of outstanding disk writes
+    pub disk_queue_size: Option<i64>,
+    /// For qBittorrent ≥ v4.4: Disk IO backend
+    pub disk_io_type: Option<DiskIoType>,
+    /// For qBittorrent ≥ v4.4: How files are read from disk
+    pub disk_io_read_mode: Option<DiskIoReadMode>,
+    /// For qBittorrent ≥ v4.4: How files are written to disk
+    pub disk_io_write_mode: Option<DiskIoWriteMode>,
+    /// For qBittorrent ≥ v4.6: True enables I2P
+    pub i2p_enabled: Option<bool>,
+    /// For qBittorrent ≥ v4.6: Address of the I2P SAM bridge
+    pub i2p_address: Option<String>,
+    /// For qBittorrent ≥ v4.6: Port of the I2P SAM bridge
+    pub i2p_port: Option<i64>,
+    /// For qBittorrent ≥ v4.6: True allows regular peers alongside I2P ones
+    pub i2p_mixed_mode: Option<bool>,
+    /// For qBittorrent ≥ v4.6: Number of I2P inbound tunnels
+    pub i2p_inbound_quantity: Option<i64>,
+    /// For qBittorrent ≥ v4.6: Number of I2P outbound tunnels
+    pub i2p_outbound_quantity: Option<i64>,
+    /// For qBittorrent ≥ v4.6: Length of I2P inbound tunnels
+    pub i2p_inbound_length: Option<i64>,
+    /// For qBittorrent ≥ v4.6: Length of I2P outbound tunnels
+    pub i2p_outbound_length: Option<i64>,
     /// Disk cache used in MiB
     pub disk_cache: Option<i64>,
     /// Disk cache expiry interval in seconds
@@ -350,10 +386,316 @@ pub struct Preferences {
     pub utp_tcp_mixed_mode: Option<i64>,
 }
 
+impl Preferences {
+    /// Watch `watch` for .torrent files and add them to `target`, see
+    /// `scan_dirs`. Replaces the target if `watch` is already watched.
+    pub fn add_scan_dir(&mut self, watch: impl Into<PathBuf>, target: ScanDirValue) {
+        self.scan_dirs
+            .get_or_insert_with(HashMap::new)
+            .insert(watch.into(), target);
+    }
+
+    /// Typed [`Preferences::dyndns_service`]. Set it with
+    /// `dyndns_service: Some(DynDnsService::NoIp.into())`.
+    pub fn dyndns_service_kind(&self) -> Option<DynDnsService> {
+        self.dyndns_service.map(DynDnsService::from)
+    }
+
+    /// Proxy preferences assembled into one [`ProxyConfig`]. Returns `None`
+    /// when the proxy is disabled, or when its type, host or port is missing
+    /// or invalid.
+    ///
+    /// Handles both forms of `proxy_type`: the integers of older versions,
+    /// which also tell whether authentication is used, and the strings of
+    /// newer ones, which rely on `proxy_auth_enabled` instead.
+    pub fn proxy_config(&self) -> Option<ProxyConfig> {
+        let (proxy_type, auth) = match self.proxy_type.as_ref()? {
+            IntOrStr::Int(1) => (ProxyType::Http, false),
+            IntOrStr::Int(2) => (ProxyType::Socks5, false),
+            IntOrStr::Int(3) => (ProxyType::Http, true),
+            IntOrStr::Int(4) => (ProxyType::Socks5, true),
+            IntOrStr::Int(5) => (ProxyType::Socks4, false),
+            IntOrStr::Int(_) => return None,
+            IntOrStr::Str(s) => {
+                let proxy_type = match s.as_str() {
+                    "HTTP" => ProxyType::Http,
+                    "SOCKS4" => ProxyType::Socks4,
+                    "SOCKS5" => ProxyType::Socks5,
+                    _ => return None,
+                };
+                (proxy_type, self.proxy_auth_enabled == Some(true))
+            }
+        };
+        let host = self.proxy_ip.clone().filter(|host| !host.is_empty())?;
+        let port = u16::try_from(self.proxy_port?).ok()?;
+
+        // SOCKS4 has no authentication
+        let auth = (auth && proxy_type != ProxyType::Socks4)
+            .then(|| {
+                Some(ProxyAuth {
+                    username: self.proxy_username.clone()?,
+                    password: self.proxy_password.clone().unwrap_or_default(),
+                })
+            })
+            .flatten();
+
+        Some(ProxyConfig {
+            proxy_type,
+            host,
+            port,
+            auth,
+            peer_connections: self.proxy_peer_connections,
+            torrents_only: self.proxy_torrents_only,
+        })
+    }
+
+    /// Start and end time of the alternative speed limits schedule. Returns
+    /// `None` when the scheduler is disabled, or when any of the hours and
+    /// minutes is missing or out of range.
+    #[cfg(feature = "time")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "time")))]
+    pub fn schedule_window(&self) -> Option<(NaiveTime, NaiveTime)> {
+        if self.scheduler_enabled != Some(true) {
+            return None;
+        }
+
+        let time = |hour: Option<i64>, min: Option<i64>| {
+            NaiveTime::from_hms_opt(hour?.try_into().ok()?, min?.try_into().ok()?, 0)
+        };
+        Some((
+            time(self.schedule_from_hour, self.schedule_from_min)?,
+            time(self.schedule_to_hour, self.schedule_to_min)?,
+        ))
+    }
+}
+
+/// Preferences to change with [`Qbit::set_preferences_patch`]. Unlike
+/// [`Preferences`], which can only leave a preference unchanged (`None`) or set
+/// it (`Some`), each preference is in one of three states:
+///
+/// - Unchanged: `None` in `set` and not listed in `clear`
+/// - Set: `Some` in `set`
+/// - Cleared: listed in `clear`, sent as an empty string. This takes
+///   precedence over `set`
+///
+/// Clearing is meant for string preferences, e.g. blanking `export_dir`
+/// disables copying .torrent files.
+///
+/// [`Qbit::set_preferences_patch`]: crate::Qbit::set_preferences_patch
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PreferencesPatch {
+    /// Preferences to set
+    pub set: Preferences,
+    /// Names of preferences to clear, as used by the API (e.g. `export_dir`)
+    pub clear: Vec<String>,
+}
+
+impl PreferencesPatch {
+    pub fn new(set: Preferences) -> Self {
+        Self {
+            set,
+            clear: Vec::new(),
+        }
+    }
+
+    /// Clear the preference with the given API name
+    pub fn clear(mut self, name: impl Into<String>) -> Self {
+        self.clear.push(name.into());
+        self
+    }
+}
+
+impl From<Preferences> for PreferencesPatch {
+    fn from(set: Preferences) -> Self {
+        Self::new(set)
+    }
+}
+
+impl Serialize for PreferencesPatch {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = match serde_json::to_value(&self.set).map_err(serde::ser::Error::custom)? {
+            serde_json::Value::Object(map) => map,
+            _ => unreachable!("Preferences should serialize to a map"),
+        };
+        for name in &self.clear {
+            map.insert(name.clone(), serde_json::Value::String(String::new()));
+        }
+        map.serialize(serializer)
+    }
+}
+
+/// DDNS service, see [`Preferences::dyndns_service_kind`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DynDnsService {
+    DynDns,
+    NoIp,
+    /// Value not known by this crate, e.g. added by a newer version
+    Other(i64),
+}
+
+impl From<i64> for DynDnsService {
+    fn from(value: i64) -> Self {
+        match value {
+            0 => Self::DynDns,
+            1 => Self::NoIp,
+            other => Self::Other(other),
+        }
+    }
+}
+
+impl From<DynDnsService> for i64 {
+    fn from(value: DynDnsService) -> Self {
+        match value {
+            DynDnsService::DynDns => 0,
+            DynDnsService::NoIp => 1,
+            DynDnsService::Other(other) => other,
+        }
+    }
+}
+
+/// Type of proxy, see [`ProxyConfig`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyType {
+    Http,
+    Socks4,
+    Socks5,
+}
+
+/// Proxy preferences returned by [`Preferences::proxy_config`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyConfig {
+    pub proxy_type: ProxyType,
+    /// IP address or domain name
+    pub host: String,
+    pub port: u16,
+    /// Credentials, if authentication is enabled
+    pub auth: Option<ProxyAuth>,
+    /// See [`Preferences::proxy_peer_connections`]
+    pub peer_connections: Option<bool>,
+    /// See [`Preferences::proxy_torrents_only`]
+    pub torrents_only: Option<bool>,
+}
+
+/// Credentials used to authenticate with a proxy, see [`ProxyConfig::auth`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyAuth {
+    pub username: String,
+    pub password: String,
+}
+
+/// Disk IO backend, see [`Preferences::disk_io_type`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "i64", into = "i64")]
+pub enum DiskIoType {
+    /// Memory mapped files where supported, POSIX otherwise
+    Default,
+    MemoryMapped,
+    Posix,
+    /// For qBittorrent ≥ v5.0
+    SimplePreadPwrite,
+    /// Value not known by this crate, e.g. added by a newer version
+    Other(i64),
+}
+
+impl From<i64> for DiskIoType {
+    fn from(value: i64) -> Self {
+        match value {
+            0 => Self::Default,
+            1 => Self::MemoryMapped,
+            2 => Self::Posix,
+            3 => Self::SimplePreadPwrite,
+            other => Self::Other(other),
+        }
+    }
+}
+
+impl From<DiskIoType> for i64 {
+    fn from(value: DiskIoType) -> Self {
+        match value {
+            DiskIoType::Default => 0,
+            DiskIoType::MemoryMapped => 1,
+            DiskIoType::Posix => 2,
+            DiskIoType::SimplePreadPwrite => 3,
+            DiskIoType::Other(other) => other,
+        }
+    }
+}
+
+/// How files are read from disk, see [`Preferences::disk_io_read_mode`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "i64", into = "i64")]
+pub enum DiskIoReadMode {
+    DisableOsCache,
+    EnableOsCache,
+    /// Value not known by this crate, e.g. added by a newer version
+    Other(i64),
+}
+
+impl From<i64> for DiskIoReadMode {
+    fn from(value: i64) -> Self {
+        match value {
+            0 => Self::DisableOsCache,
+            1 => Self::EnableOsCache,
+            other => Self::Other(other),
+        }
+    }
+}
+
+impl From<DiskIoReadMode> for i64 {
+    fn from(value: DiskIoReadMode) -> Self {
+        match value {
+            DiskIoReadMode::DisableOsCache => 0,
+            DiskIoReadMode::EnableOsCache => 1,
+            DiskIoReadMode::Other(other) => other,
+        }
+    }
+}
+
+/// How files are written to disk, see [`Preferences::disk_io_write_mode`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "i64", into = "i64")]
+pub enum DiskIoWriteMode {
+    DisableOsCache,
+    EnableOsCache,
+    /// For qBittorrent ≥ v4.5
+    WriteThrough,
+    /// Value not known by this crate, e.g. added by a newer version
+    Other(i64),
+}
+
+impl From<i64> for DiskIoWriteMode {
+    fn from(value: i64) -> Self {
+        match value {
+            0 => Self::DisableOsCache,
+            1 => Self::EnableOsCache,
+            2 => Self::WriteThrough,
+            other => Self::Other(other),
+        }
+    }
+}
+
+impl From<DiskIoWriteMode> for i64 {
+    fn from(value: DiskIoWriteMode) -> Self {
+        match value {
+            DiskIoWriteMode::DisableOsCache => 0,
+            DiskIoWriteMode::EnableOsCache => 1,
+            DiskIoWriteMode::WriteThrough => 2,
+            DiskIoWriteMode::Other(other) => other,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum ScanDirValue {
+    /// Download to the monitored folder itself
     MonitoredFolder,
+    /// Download to the default save path
     DefaultSavingPath,
+    /// Download to this path. A relative path is resolved against the
+    /// monitored folder, e.g. `sub` for a subfolder of it
     Path(PathBuf),
 }
 
@@ -365,7 +707,10 @@ impl Serialize for ScanDirValue {
         match self {
             ScanDirValue::MonitoredFolder => serializer.serialize_i64(0),
             ScanDirValue::DefaultSavingPath => serializer.serialize_i64(1),
-            ScanDirValue::Path(path) => serializer.serialize_str(path.to_str().unwrap()),
+            ScanDirValue::Path(path) => serializer.serialize_str(
+                path.to_str()
+                    .ok_or_else(|| serde::ser::Error::custom("Path is not valid UTF-8"))?,
+            ),
         }
     }
 }
@@ -388,6 +733,7 @@ struct ScanDirsVisitor;
 /// `0`                         | Download to the monitored folder
 /// `1`                         | Download to the default save path
 /// `"/path/to/download/to"`    | Download to this path
+/// `"relative/path"`           | Download to this path inside the monitored folder
 impl Visitor<'_> for ScanDirsVisitor {
     type Value = ScanDirValue;
 
@@ -406,6 +752,16 @@ impl Visitor<'_> for ScanDirsVisitor {
         }
     }
 
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        match i64::try_from(v) {
+            Ok(v) => self.visit_i64(v),
+            Err(_) => Err(E::custom(format!("Invalid value for ScanDirs: {}", v))),
+        }
+    }
+
     fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
     where
         E: serde::de::Error,
@@ -413,3 +769,260 @@ impl Visitor<'_> for ScanDirsVisitor {
         Ok(ScanDirValue::Path(PathBuf::from(v)))
     }
 }
+
+#[test]
+fn test_preferences_patch() {
+    let patch = PreferencesPatch::new(Preferences {
+        locale: Some("en_GB".to_owned()),
+        export_dir: Some("/torrents".to_owned()),
+        ..Preferences::default()
+    })
+    .clear("export_dir")
+    .clear("export_dir_fin");
+
+    assert_eq!(
+        serde_json::to_value(&patch).unwrap(),
+        serde_json::json!({
+            "locale": "en_GB",
+            "export_dir": "",
+            "export_dir_fin": "",
+        })
+    );
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn test_schedule_window() {
+    let preferences = Preferences {
+        scheduler_enabled: Some(true),
+        schedule_from_hour: Some(8),
+        schedule_from_min: Some(30),
+        schedule_to_hour: Some(20),
+        schedule_to_min: Some(0),
+        ..Preferences::default()
+    };
+    assert_eq!(
+        preferences.schedule_window(),
+        Some((
+            NaiveTime::from_hms_opt(8, 30, 0).unwrap(),
+            NaiveTime::from_hms_opt(20, 0, 0).unwrap()
+        ))
+    );
+
+    let disabled = Preferences {
+        scheduler_enabled: Some(false),
+        ..preferences.clone()
+    };
+    assert_eq!(disabled.schedule_window(), None);
+
+    let invalid = Preferences {
+        schedule_to_hour: Some(24),
+        ..preferences
+    };
+    assert_eq!(invalid.schedule_window(), None);
+}
+
+#[test]
+fn test_preferences_newer_fields() {
+    let preferences: Preferences = serde_json::from_value(serde_json::json!({
+        "locale": "en",
+        "current_interface_name": "eth0",
+        "memory_working_set_limit": 512,
+        "hashing_threads": 2,
+        "disk_io_type": 1,
+        "disk_io_read_mode": 1,
+        "disk_io_write_mode": 2,
+        "i2p_enabled": true,
+        "i2p_address": "127.0.0.1",
+        "i2p_port": 7656,
+    }))
+    .unwrap();
+
+    assert_eq!(preferences.current_interface_name.as_deref(), Some("eth0"));
+    assert_eq!(preferences.memory_working_set_limit, Some(512));
+    assert_eq!(preferences.hashing_threads, Some(2));
+    assert_eq!(preferences.disk_io_type, Some(DiskIoType::MemoryMapped));
+    assert_eq!(
+        preferences.disk_io_read_mode,
+        Some(DiskIoReadMode::EnableOsCache)
+    );
+    assert_eq!(
+        preferences.disk_io_write_mode,
+        Some(DiskIoWriteMode::WriteThrough)
+    );
+    assert_eq!(preferences.i2p_enabled, Some(true));
+    assert_eq!(preferences.i2p_port, Some(7656));
+    assert_eq!(preferences.i2p_mixed_mode, None);
+
+    let json = serde_json::to_value(&preferences).unwrap();
+    assert_eq!(json["disk_io_write_mode"], 2);
+}
+
+#[test]
+fn test_disk_io_unknown_values() {
+    let preferences: Preferences = serde_json::from_value(serde_json::json!({
+        "disk_io_type": 3,
+        "disk_io_read_mode": 7,
+        "disk_io_write_mode": 9,
+    }))
+    .unwrap();
+
+    assert_eq!(
+        preferences.disk_io_type,
+        Some(DiskIoType::SimplePreadPwrite)
+    );
+    assert_eq!(preferences.disk_io_read_mode, Some(DiskIoReadMode::Other(7)));
+    assert_eq!(
+        preferences.disk_io_write_mode,
+        Some(DiskIoWriteMode::Other(9))
+    );
+
+    // Unknown values are sent back unchanged
+    let json = serde_json::to_value(&preferences).unwrap();
+    assert_eq!(json["disk_io_type"], 3);
+    assert_eq!(json["disk_io_read_mode"], 7);
+    assert_eq!(json["disk_io_write_mode"], 9);
+    assert_eq!(DiskIoType::from(42), DiskIoType::Other(42));
+}
+
+#[test]
+fn test_add_scan_dir() {
+    let mut preferences = Preferences::default();
+    preferences.add_scan_dir("/watch/a", ScanDirValue::MonitoredFolder);
+    preferences.add_scan_dir("/watch/b", ScanDirValue::Path("/downloads".into()));
+
+    assert_eq!(
+        serde_json::to_value(&preferences).unwrap(),
+        serde_json::json!({
+            "scan_dirs": {
+                "/watch/a": 0,
+                "/watch/b": "/downloads",
+            },
+        })
+    );
+}
+
+#[test]
+fn test_scan_dir_value() {
+    let cases = [
+        (serde_json::json!(0), ScanDirValue::MonitoredFolder),
+        (serde_json::json!(1), ScanDirValue::DefaultSavingPath),
+        (
+            serde_json::json!("/downloads"),
+            ScanDirValue::Path("/downloads".into()),
+        ),
+        (
+            serde_json::json!("sub/folder"),
+            ScanDirValue::Path("sub/folder".into()),
+        ),
+    ];
+    for (json, value) in cases {
+        assert_eq!(
+            serde_json::from_value::<ScanDirValue>(json.clone()).unwrap(),
+            value
+        );
+        assert_eq!(serde_json::to_value(&value).unwrap(), json);
+    }
+
+    assert!(serde_json::from_value::<ScanDirValue>(serde_json::json!(2)).is_err());
+
+    let dirs: HashMap<PathBuf, ScanDirValue> =
+        serde_json::from_str(r#"{"/watch/a": 0, "/watch/b": 1, "/watch/c": "/downloads"}"#)
+            .unwrap();
+    assert_eq!(dirs.len(), 3);
+}
+
+#[test]
+fn test_dyndns_service() {
+    let preferences: Preferences =
+        serde_json::from_value(serde_json::json!({ "dyndns_service": 1 })).unwrap();
+    assert_eq!(preferences.dyndns_service_kind(), Some(DynDnsService::NoIp));
+    assert_eq!(Preferences::default().dyndns_service_kind(), None);
+
+    // Unknown services don't fail the whole preferences
+    let preferences: Preferences =
+        serde_json::from_value(serde_json::json!({ "dyndns_service": 2 })).unwrap();
+    assert_eq!(
+        preferences.dyndns_service_kind(),
+        Some(DynDnsService::Other(2))
+    );
+
+    let preferences = Preferences {
+        dyndns_service: Some(DynDnsService::DynDns.into()),
+        ..Preferences::default()
+    };
+    assert_eq!(
+        serde_json::to_value(&preferences).unwrap(),
+        serde_json::json!({ "dyndns_service": 0 })
+    );
+}
+
+#[test]
+fn test_proxy_config() {
+    let preferences = Preferences {
+        proxy_type: Some(IntOrStr::Str("SOCKS5".to_owned())),
+        proxy_ip: Some("proxy.example".to_owned()),
+        proxy_port: Some(1080),
+        proxy_auth_enabled: Some(true),
+        proxy_username: Some("user".to_owned()),
+        proxy_password: Some("pass".to_owned()),
+        proxy_torrents_only: Some(true),
+        ..Preferences::default()
+    };
+    assert_eq!(
+        preferences.proxy_config(),
+        Some(ProxyConfig {
+            proxy_type: ProxyType::Socks5,
+            host: "proxy.example".to_owned(),
+            port: 1080,
+            auth: Some(ProxyAuth {
+                username: "user".to_owned(),
+                password: "pass".to_owned(),
+            }),
+            peer_connections: None,
+            torrents_only: Some(true),
+        })
+    );
+
+    // Older versions encode authentication in the type
+    let legacy = |proxy_type| {
+        Preferences {
+            proxy_type: Some(IntOrStr::Int(proxy_type)),
+            proxy_auth_enabled: None,
+            ..preferences.clone()
+        }
+        .proxy_config()
+        .map(|config| (config.proxy_type, config.auth.is_some()))
+    };
+    assert_eq!(legacy(-1), None);
+    assert_eq!(legacy(0), None);
+    assert_eq!(legacy(1), Some((ProxyType::Http, false)));
+    assert_eq!(legacy(2), Some((ProxyType::Socks5, false)));
+    assert_eq!(legacy(3), Some((ProxyType::Http, true)));
+    assert_eq!(legacy(4), Some((ProxyType::Socks5, true)));
+    assert_eq!(legacy(5), Some((ProxyType::Socks4, false)));
+
+    let no_auth = Preferences {
+        proxy_auth_enabled: Some(false),
+        ..preferences.clone()
+    };
+    assert_eq!(no_auth.proxy_config().unwrap().auth, None);
+
+    let socks4 = Preferences {
+        proxy_type: Some(IntOrStr::Str("SOCKS4".to_owned())),
+        ..preferences.clone()
+    };
+    assert_eq!(socks4.proxy_config().unwrap().auth, None);
+
+    let disabled = Preferences {
+        proxy_type: Some(IntOrStr::Str("None".to_owned())),
+        ..preferences.clone()
+    };
+    assert_eq!(disabled.proxy_config(), None);
+
+    let bad_port = Preferences {
+        proxy_port: Some(70000),
+        ..preferences
+    };
+    assert_eq!(bad_port.proxy_config(), None);
+}
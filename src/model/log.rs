@@ -2,7 +2,7 @@ use std::net::IpAddr;
 
 use serde_with::skip_serializing_none;
 
-#[derive(Debug, Clone, serde::Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
 
 pub struct Log {
     /// ID of the message
@@ -17,7 +17,92 @@ pub struct Log {
     pub log_type: i8,
 }
 
-#[derive(Debug, Clone, serde::Deserialize, PartialEq, Eq)]
+impl Log {
+    /// Recognize common events from the message, see [`LogEvent`]. This is
+    /// best-effort: messages are not meant to be parsed and their format
+    /// changes between versions, so anything not recognized is
+    /// [`LogEvent::Other`].
+    pub fn classify(&self) -> LogEvent {
+        let message = self.message.as_str();
+        let field = |key| quoted_field(message, key);
+
+        if message.starts_with("Added new torrent.") {
+            if let Some(name) = field("Torrent") {
+                return LogEvent::TorrentAdded { name };
+            }
+        }
+        if message.starts_with("Torrent download finished.") {
+            if let Some(name) = field("Torrent") {
+                return LogEvent::TorrentFinished { name };
+            }
+        }
+        if message.starts_with("Tracker error") || message.starts_with("Failed to announce") {
+            if let (Some(torrent), Some(tracker)) = (field("Torrent"), field("Tracker")) {
+                let error = field("Error").or_else(|| field("Reason")).unwrap_or_default();
+                return LogEvent::TrackerError {
+                    torrent,
+                    tracker,
+                    error,
+                };
+            }
+        }
+        if message.contains("IP has been banned") {
+            let ip = message
+                .split_once("IP: ")
+                .and_then(|(_, rest)| rest.split(',').next()?.trim().parse().ok());
+            if let Some(ip) = ip {
+                return LogEvent::IpBanned { ip };
+            }
+        }
+        // Formats used before qBittorrent 4.4
+        if let Some(name) = old_format(message, " added to download list.") {
+            return LogEvent::TorrentAdded { name };
+        }
+        if let Some(name) = old_format(message, " has finished downloading.") {
+            return LogEvent::TorrentFinished { name };
+        }
+
+        LogEvent::Other(self.message.clone())
+    }
+}
+
+/// Value of `key: "value"` in a log message
+fn quoted_field(message: &str, key: &str) -> Option<String> {
+    let (_, rest) = message.split_once(&format!("{key}: \""))?;
+    let (value, _) = rest.split_once('"')?;
+    Some(value.to_owned())
+}
+
+/// Name in a `'name' suffix` log message
+fn old_format(message: &str, suffix: &str) -> Option<String> {
+    message
+        .strip_suffix(suffix)?
+        .strip_prefix('\'')?
+        .strip_suffix('\'')
+        .map(str::to_owned)
+}
+
+/// Event recognized from a log message, see [`Log::classify`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogEvent {
+    /// A torrent was added
+    TorrentAdded { name: String },
+    /// A torrent finished downloading
+    TorrentFinished { name: String },
+    /// A tracker returned an error or could not be reached
+    TrackerError {
+        torrent: String,
+        tracker: String,
+        /// Empty if not given
+        error: String,
+    },
+    /// An IP was banned from the WebUI for too many failed login attempts
+    IpBanned { ip: IpAddr },
+    /// Any other message, as is
+    Other(String),
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
 pub struct PeerLog {
     /// ID of the peer
     pub id: i64,
@@ -66,6 +151,111 @@ pub struct GetLogsArg {
     pub warning: Option<bool>,
     /// Include critical messages (default: `true`)
     pub critical: Option<bool>,
-    /// Exclude messages with "message id" <= `last_known_id` (default: `-1`)
+    /// Exclude messages with "message id" <= `last_known_id` (default: `-1`,
+    /// which includes every message). Feed back the largest [`Log::id`]
+    /// received to only get new messages.
+    ///
+    /// Values below `-1` are sent as `-1`, and values above [`i32::MAX`], which
+    /// qBittorrent can't parse and would treat as `0`, as [`i32::MAX`].
     pub last_known_id: Option<i64>,
 }
+
+impl GetLogsArg {
+    /// Include messages of all levels after `last_known_id`
+    pub fn all_levels(last_known_id: impl Into<Option<i64>>) -> Self {
+        Self {
+            normal: Some(true),
+            info: Some(true),
+            warning: Some(true),
+            critical: Some(true),
+            last_known_id: last_known_id.into(),
+        }
+    }
+}
+
+#[test]
+fn test_log_roundtrip() {
+    let log = Log {
+        id: 1,
+        message: "qBittorrent v4.6.2 started".to_owned(),
+        timestamp: 1_700_000_000_000,
+        log_type: 2,
+    };
+    let json = serde_json::to_value(&log).unwrap();
+    assert_eq!(json["type"], 2);
+    assert_eq!(serde_json::from_value::<Log>(json).unwrap(), log);
+
+    let peer_log = PeerLog {
+        id: 3,
+        ip: "1.2.3.4".parse().unwrap(),
+        timestamp: 1_700_000_000_000,
+        blocked: true,
+        reason: Some("banned".to_owned()),
+    };
+    let json = serde_json::to_string(&peer_log).unwrap();
+    assert_eq!(serde_json::from_str::<PeerLog>(&json).unwrap(), peer_log);
+}
+
+#[test]
+fn test_get_logs_arg_all_levels() {
+    let arg = GetLogsArg::all_levels(42);
+    assert_eq!(arg.normal, Some(true));
+    assert_eq!(arg.info, Some(true));
+    assert_eq!(arg.warning, Some(true));
+    assert_eq!(arg.critical, Some(true));
+    assert_eq!(arg.last_known_id, Some(42));
+
+    assert_eq!(GetLogsArg::all_levels(None).last_known_id, None);
+}
+
+#[test]
+fn test_log_classify() {
+    let classify = |message: &str| {
+        Log {
+            id: 0,
+            message: message.to_owned(),
+            timestamp: 0,
+            log_type: 2,
+        }
+        .classify()
+    };
+
+    assert_eq!(
+        classify(r#"Added new torrent. Torrent: "ubuntu-24.04.iso""#),
+        LogEvent::TorrentAdded {
+            name: "ubuntu-24.04.iso".to_owned()
+        }
+    );
+    assert_eq!(
+        classify("'ubuntu-24.04.iso' added to download list."),
+        LogEvent::TorrentAdded {
+            name: "ubuntu-24.04.iso".to_owned()
+        }
+    );
+    assert_eq!(
+        classify(r#"Torrent download finished. Torrent: "ubuntu-24.04.iso""#),
+        LogEvent::TorrentFinished {
+            name: "ubuntu-24.04.iso".to_owned()
+        }
+    );
+    assert_eq!(
+        classify(
+            r#"Tracker error. Torrent: "ubuntu-24.04.iso". Tracker: "https://tracker.example.org/announce". Error: "unregistered torrent""#
+        ),
+        LogEvent::TrackerError {
+            torrent: "ubuntu-24.04.iso".to_owned(),
+            tracker: "https://tracker.example.org/announce".to_owned(),
+            error: "unregistered torrent".to_owned(),
+        }
+    );
+    assert_eq!(
+        classify("WebAPI login failure. Reason: IP has been banned, IP: 192.168.1.7, username: admin"),
+        LogEvent::IpBanned {
+            ip: "192.168.1.7".parse().unwrap()
+        }
+    );
+    assert_eq!(
+        classify("qBittorrent v4.6.2 started"),
+        LogEvent::Other("qBittorrent v4.6.2 started".to_owned())
+    );
+}
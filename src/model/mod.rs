@@ -1,8 +1,9 @@
 //! Model types used in the API.
 
 use std::{
+    collections::BTreeMap,
     fmt::{Display, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
     str::FromStr,
 };
 
@@ -41,6 +42,23 @@ impl Credential {
     }
 }
 
+/// Error returned when parsing a [`Credential`] without a `:`
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Invalid credential, expected `username:password`")]
+pub struct ParseCredentialError;
+
+/// Parse `username:password`. Only the first `:` separates them, so the
+/// password may contain colons but the username may not.
+impl FromStr for Credential {
+    type Err = ParseCredentialError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split_once(':')
+            .map(|(username, password)| Self::new(username, password))
+            .ok_or(ParseCredentialError)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Category {
@@ -48,7 +66,7 @@ pub struct Category {
     pub save_path: PathBuf,
 }
 
-#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub struct Tracker {
     /// Tracker url
     pub url: String,
@@ -72,6 +90,78 @@ pub struct Tracker {
     pub msg: String,
 }
 
+impl Tracker {
+    /// Tier under which [`group_trackers_by_tier`] places special entries
+    /// without a real tier (DHT, PeX and LSD).
+    pub const SPECIAL_TIER: i64 = -1;
+}
+
+/// Trackers grouped by tier, see [`group_trackers_by_tier`].
+pub type TrackerTiers<'a> = BTreeMap<i64, Vec<&'a Tracker>>;
+
+/// Group trackers by their tier, in the order they are tried. Special entries
+/// with a negative tier (DHT, PeX and LSD) are grouped together under
+/// [`Tracker::SPECIAL_TIER`], which sorts before all regular tiers like in
+/// qBittorrent's UI.
+pub fn group_trackers_by_tier(trackers: &[Tracker]) -> TrackerTiers<'_> {
+    trackers
+        .iter()
+        .fold(TrackerTiers::new(), |mut tiers, tracker| {
+            tiers
+                .entry(tracker.tier.max(Tracker::SPECIAL_TIER))
+                .or_default()
+                .push(tracker);
+            tiers
+        })
+}
+
+/// Trackers that qBittorrent announces to, in order, given the
+/// `announce_to_all_tiers` and `announce_to_all_trackers` preferences.
+/// Special entries (DHT, PeX and LSD) are left out.
+///
+/// Tiers are tried from the lowest. Within a tier, qBittorrent announces to
+/// every tracker if `all_trackers` is set, otherwise to the first one that is
+/// not [`TrackerStatus::NotWorking`]. Likewise it announces to every tier if
+/// `all_tiers` is set, otherwise to the first tier with such a tracker. When
+/// every tracker fails, the first one is still tried.
+pub fn announce_order(trackers: &[Tracker], all_tiers: bool, all_trackers: bool) -> Vec<&Tracker> {
+    let usable = |tracker: &Tracker| tracker.status != TrackerStatus::NotWorking;
+    let tiers = group_trackers_by_tier(trackers)
+        .into_iter()
+        .filter(|(tier, _)| *tier != Tracker::SPECIAL_TIER)
+        .map(|(_, trackers)| trackers)
+        .collect::<Vec<_>>();
+
+    let tiers = if all_tiers {
+        tiers
+    } else {
+        tiers
+            .iter()
+            .find(|trackers| trackers.iter().any(|t| usable(t)))
+            .or(tiers.first())
+            .into_iter()
+            .cloned()
+            .collect()
+    };
+
+    tiers
+        .into_iter()
+        .flat_map(|trackers| {
+            if all_trackers {
+                trackers
+            } else {
+                trackers
+                    .iter()
+                    .find(|t| usable(t))
+                    .or(trackers.first())
+                    .into_iter()
+                    .copied()
+                    .collect()
+            }
+        })
+        .collect()
+}
+
 #[derive(
     Debug,
     Clone,
@@ -98,6 +188,34 @@ pub enum TrackerStatus {
     NotWorking   = 4,
 }
 
+/// Number of trackers of a torrent in each [`TrackerStatus`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrackerHealth {
+    pub working: usize,
+    pub not_working: usize,
+    pub updating: usize,
+    pub not_contacted: usize,
+    /// Includes the DHT, PeX and LSD entries
+    pub disabled: usize,
+}
+
+impl TrackerHealth {
+    pub fn from_trackers(trackers: &[Tracker]) -> Self {
+        trackers
+            .iter()
+            .fold(Self::default(), |mut health, tracker| {
+                match tracker.status {
+                    TrackerStatus::Working => health.working += 1,
+                    TrackerStatus::NotWorking => health.not_working += 1,
+                    TrackerStatus::Updating => health.updating += 1,
+                    TrackerStatus::NotContacted => health.not_contacted += 1,
+                    TrackerStatus::Disabled => health.disabled += 1,
+                }
+                health
+            })
+    }
+}
+
 /// Type that can be either an integer or a string.
 #[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(untagged)]
@@ -115,11 +233,133 @@ impl Display for IntOrStr {
     }
 }
 
+/// URL as sent by the API, kept as a string when it's not a valid
+/// [`Url`](url::Url).
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum UrlOrRaw {
+    Url(url::Url),
+    Raw(String),
+}
+
+impl UrlOrRaw {
+    pub fn as_str(&self) -> &str {
+        match self {
+            UrlOrRaw::Url(url) => url.as_str(),
+            UrlOrRaw::Raw(raw) => raw,
+        }
+    }
+
+    /// The parsed URL, or `None` if it's not valid
+    pub fn as_url(&self) -> Option<&url::Url> {
+        match self {
+            UrlOrRaw::Url(url) => Some(url),
+            UrlOrRaw::Raw(_) => None,
+        }
+    }
+}
+
+impl Display for UrlOrRaw {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Error returned when parsing an enum from a string that doesn't match any of
+/// its variants.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Invalid {kind} {value:?}, expected one of: {}", .expected.join(", "))]
+pub struct ParseEnumError {
+    kind: &'static str,
+    value: String,
+    expected: Vec<&'static str>,
+}
+
+impl ParseEnumError {
+    pub(crate) fn new(
+        kind: &'static str,
+        value: &str,
+        expected: impl IntoIterator<Item = &'static str>,
+    ) -> Self {
+        Self {
+            kind,
+            value: value.to_owned(),
+            expected: expected.into_iter().collect(),
+        }
+    }
+}
+
 /// A wrapper around `Vec<T>` that implements `FromStr` and `ToString` as
 /// `C`-separated strings where `C` is a char.
 #[derive(Debug, Clone, PartialEq, Eq, SerializeDisplay, DeserializeFromStr)]
 pub struct Sep<T, const C: char>(Vec<T>);
 
+impl<T, const C: char> Sep<T, C> {
+    pub fn as_slice(&self) -> &[T] {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn as_sep_ref(&self) -> SepRef<'_, T, C> {
+        SepRef(&self.0)
+    }
+}
+
+/// Borrowed version of [`Sep`], formatted and serialized the same way without
+/// taking ownership of the elements.
+#[derive(Debug, PartialEq, Eq, SerializeDisplay)]
+pub struct SepRef<'a, T, const C: char>(&'a [T]);
+
+impl<T, const C: char> SepRef<'_, T, C> {
+    pub fn as_slice(&self) -> &[T] {
+        self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<T, const C: char> Clone for SepRef<'_, T, C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, const C: char> Copy for SepRef<'_, T, C> {}
+
+impl<'a, T, const C: char> From<&'a [T]> for SepRef<'a, T, C> {
+    fn from(inner: &'a [T]) -> Self {
+        SepRef(inner)
+    }
+}
+
+impl<'a, T, const C: char> From<&'a Vec<T>> for SepRef<'a, T, C> {
+    fn from(inner: &'a Vec<T>) -> Self {
+        SepRef(inner)
+    }
+}
+
+impl<T: Display, const C: char> Display for SepRef<'_, T, C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            [] => Ok(()),
+            [x] => x.fmt(f),
+            [x, xs @ ..] => {
+                x.fmt(f)?;
+                for x in xs {
+                    f.write_char(C)?;
+                    x.fmt(f)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
 impl<T: FromStr, const C: char> FromStr for Sep<T, C> {
     type Err = T::Err;
 
@@ -149,20 +389,26 @@ impl<T: AsRef<str>> NonEmptyStr<T> {
     }
 }
 
+/// A wrapper around `Path` that ensures the path is non-empty.
+pub struct NonEmptyPath<T>(T);
+
+impl<T: AsRef<Path>> NonEmptyPath<T> {
+    pub fn as_path(&self) -> &Path {
+        self.0.as_ref()
+    }
+
+    pub fn new(p: T) -> Option<Self> {
+        if p.as_ref().as_os_str().is_empty() {
+            None
+        } else {
+            Some(NonEmptyPath(p))
+        }
+    }
+}
+
 impl<T: Display, const C: char> Display for Sep<T, C> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self.0.as_slice() {
-            [] => Ok(()),
-            [x] => x.fmt(f),
-            [x, xs @ ..] => {
-                x.fmt(f)?;
-                for x in xs {
-                    f.write_char(C)?;
-                    x.fmt(f)?;
-                }
-                Ok(())
-            }
-        }
+        self.as_sep_ref().fmt(f)
     }
 }
 
@@ -172,6 +418,19 @@ impl<V: Into<Vec<T>>, T, const C: char> From<V> for Sep<T, C> {
     }
 }
 
+#[test]
+fn test_credential_from_str() {
+    assert_eq!(
+        "a:b:c".parse::<Credential>().unwrap(),
+        Credential::new("a", "b:c")
+    );
+    assert_eq!(
+        "admin:".parse::<Credential>().unwrap(),
+        Credential::new("admin", "")
+    );
+    assert_eq!("admin".parse::<Credential>(), Err(ParseCredentialError));
+}
+
 #[test]
 fn test_sep() {
     let sep = Sep::<u8, '|'>::from(vec![1, 2, 3]);
@@ -186,3 +445,126 @@ fn test_sep() {
     let sep = Sep::<u8, '|'>::from(vec![]);
     assert_eq!(sep.to_string(), "");
 }
+
+#[test]
+fn test_sep_ref() {
+    let urls = vec![
+        url::Url::parse("http://a.example/announce").unwrap(),
+        url::Url::parse("udp://b.example:1337").unwrap(),
+    ];
+
+    let borrowed = SepRef::<_, '|'>::from(&urls);
+    let owned = Sep::<_, '|'>::from(urls.clone());
+    assert_eq!(borrowed.to_string(), owned.to_string());
+    assert_eq!(
+        serde_json::to_string(&borrowed).unwrap(),
+        serde_json::to_string(&owned).unwrap()
+    );
+    assert_eq!(SepRef::<u8, '|'>::from(&[][..]).to_string(), "");
+}
+
+/// Tracker without any peer statistics
+#[cfg(test)]
+fn tracker(url: &str, tier: i64, status: TrackerStatus) -> Tracker {
+    Tracker {
+        url: url.to_owned(),
+        status,
+        tier,
+        num_peers: 0,
+        num_seeds: 0,
+        num_leeches: 0,
+        num_downloaded: 0,
+        msg: String::new(),
+    }
+}
+
+#[test]
+fn test_announce_order() {
+    let trackers = vec![
+        tracker("** [DHT] **", -1, TrackerStatus::Disabled),
+        tracker("http://c.example", 1, TrackerStatus::Working),
+        tracker("http://a.example", 0, TrackerStatus::NotWorking),
+        tracker("http://b.example", 0, TrackerStatus::Working),
+        tracker("http://d.example", 1, TrackerStatus::Updating),
+    ];
+    let urls = |all_tiers, all_trackers| {
+        announce_order(&trackers, all_tiers, all_trackers)
+            .into_iter()
+            .map(|t| t.url.as_str())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(urls(false, false), ["http://b.example"]);
+    assert_eq!(urls(false, true), ["http://a.example", "http://b.example"]);
+    assert_eq!(urls(true, false), ["http://b.example", "http://c.example"]);
+    assert_eq!(
+        urls(true, true),
+        [
+            "http://a.example",
+            "http://b.example",
+            "http://c.example",
+            "http://d.example"
+        ]
+    );
+
+    // Nothing works, the first tracker is still tried
+    let failing = vec![
+        tracker("http://a.example", 0, TrackerStatus::NotWorking),
+        tracker("http://b.example", 1, TrackerStatus::NotWorking),
+    ];
+    let order = announce_order(&failing, false, false);
+    assert_eq!(order, [&failing[0]]);
+}
+
+#[test]
+fn test_tracker_health() {
+    let trackers = [
+        TrackerStatus::Disabled,
+        TrackerStatus::Disabled,
+        TrackerStatus::Disabled,
+        TrackerStatus::Working,
+        TrackerStatus::Working,
+        TrackerStatus::NotWorking,
+        TrackerStatus::Updating,
+        TrackerStatus::NotContacted,
+    ]
+    .map(|status| tracker("", 0, status));
+
+    assert_eq!(
+        TrackerHealth::from_trackers(&trackers),
+        TrackerHealth {
+            working: 2,
+            not_working: 1,
+            updating: 1,
+            not_contacted: 1,
+            disabled: 3,
+        }
+    );
+    assert_eq!(TrackerHealth::from_trackers(&[]), TrackerHealth::default());
+}
+
+#[test]
+fn test_group_trackers_by_tier() {
+    let trackers = vec![
+        tracker("** [DHT] **", -1, TrackerStatus::Working),
+        tracker("** [PeX] **", -1, TrackerStatus::Working),
+        tracker("http://b.example/announce", 1, TrackerStatus::Working),
+        tracker("http://a.example/announce", 0, TrackerStatus::Working),
+        tracker("http://c.example/announce", 1, TrackerStatus::Working),
+    ];
+
+    let tiers = group_trackers_by_tier(&trackers);
+    let urls = |tier| {
+        tiers[&tier]
+            .iter()
+            .map(|t| t.url.as_str())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(tiers.keys().copied().collect::<Vec<_>>(), [-1, 0, 1]);
+    assert_eq!(urls(Tracker::SPECIAL_TIER), ["** [DHT] **", "** [PeX] **"]);
+    assert_eq!(urls(0), ["http://a.example/announce"]);
+    assert_eq!(
+        urls(1),
+        ["http://b.example/announce", "http://c.example/announce"]
+    );
+}
@@ -0,0 +1,136 @@
+use std::{convert::Infallible, fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+
+/// Status of a search job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SearchState {
+    Running,
+    Stopped,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchStatus {
+    /// ID of the search job
+    pub id: u64,
+    /// Current status of the search job
+    pub status: SearchState,
+    /// Total number of results. If the status is `Running` this number may
+    /// continue to increase
+    pub total: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchResults {
+    /// Search results, see [`SearchResult`]
+    pub results: Vec<SearchResult>,
+    /// Current status of the search job
+    pub status: SearchState,
+    /// Total number of results. If the status is `Running` this number may
+    /// continue to increase
+    pub total: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    /// URL of the torrent's description page
+    pub descr_link: String,
+    /// Name of the file
+    pub file_name: String,
+    /// Size of the file in Bytes
+    pub file_size: i64,
+    /// Torrent download link (usually either .torrent file or magnet link)
+    pub file_url: String,
+    /// Number of leechers
+    pub nb_leechers: i64,
+    /// Number of seeders
+    pub nb_seeders: i64,
+    /// URL of the torrent site
+    pub site_url: String,
+}
+
+/// Category to search torrents in. Search plugins may define their own
+/// categories, which are represented by [`SearchCategory::Custom`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, SerializeDisplay, DeserializeFromStr)]
+pub enum SearchCategory {
+    #[default]
+    All,
+    Movies,
+    Tv,
+    Music,
+    Games,
+    Anime,
+    Software,
+    Pictures,
+    Books,
+    /// Category defined by a search plugin
+    Custom(String),
+}
+
+impl SearchCategory {
+    fn as_str(&self) -> &str {
+        match self {
+            Self::All => "all",
+            Self::Movies => "movies",
+            Self::Tv => "tv",
+            Self::Music => "music",
+            Self::Games => "games",
+            Self::Anime => "anime",
+            Self::Software => "software",
+            Self::Pictures => "pictures",
+            Self::Books => "books",
+            Self::Custom(category) => category,
+        }
+    }
+}
+
+impl Display for SearchCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SearchCategory {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "all" => Self::All,
+            "movies" => Self::Movies,
+            "tv" => Self::Tv,
+            "music" => Self::Music,
+            "games" => Self::Games,
+            "anime" => Self::Anime,
+            "software" => Self::Software,
+            "pictures" => Self::Pictures,
+            "books" => Self::Books,
+            _ => Self::Custom(s.to_owned()),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub(crate) struct SearchIdArg {
+    pub(crate) id: u64,
+}
+
+#[test]
+fn test_search_category() {
+    assert_eq!(
+        serde_json::to_string(&SearchCategory::Movies).unwrap(),
+        r#""movies""#
+    );
+    assert_eq!(
+        serde_json::from_str::<SearchCategory>(r#""tv""#).unwrap(),
+        SearchCategory::Tv
+    );
+
+    let custom = SearchCategory::Custom("ebooks".to_owned());
+    assert_eq!(serde_json::to_string(&custom).unwrap(), r#""ebooks""#);
+    assert_eq!(
+        serde_json::from_str::<SearchCategory>(r#""ebooks""#).unwrap(),
+        custom
+    );
+}
@@ -1,10 +1,15 @@
-use std::{collections::HashMap, net::SocketAddr};
+use std::{
+    collections::{HashMap, HashSet},
+    net::{IpAddr, SocketAddr},
+};
 
 use serde_value::Value;
+use serde_with::{serde_as, skip_serializing_none, DisplayFromStr, PickFirst};
+use tap::Pipe;
 
-use crate::model::{Category, Torrent};
+use crate::model::{Category, ConnectionStatus, Torrent, TransferInfo};
 
-#[derive(Debug, Clone, serde::Deserialize, PartialEq)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
 pub struct SyncData {
     /// Response ID
     pub rid: i64,
@@ -28,22 +33,303 @@ pub struct SyncData {
     /// List of tracker URLs removed since last request
     pub trackers_removed: Option<Vec<String>>,
     /// Global transfer info
-    pub server_state: Option<HashMap<String, Value>>,
+    pub server_state: Option<ServerState>,
 }
 
-#[derive(Debug, Clone, serde::Deserialize, PartialEq)]
+/// Main data kept up to date by applying each [`SyncData`] returned by
+/// [`Qbit::sync`](crate::Qbit::sync), see [`SyncState::apply`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncState {
+    /// Response ID to send with the next request
+    pub rid: i64,
+    /// Property: torrent hash
+    pub torrents: HashMap<String, Torrent>,
+    /// Property: category name
+    pub categories: HashMap<String, Category>,
+    pub tags: HashSet<String>,
+    /// Property: tracker URL, value: hashes of the torrents that have it
+    pub trackers: HashMap<String, Vec<String>>,
+    pub server_state: ServerState,
+}
+
+impl SyncState {
+    /// Apply an update. A full update replaces everything, otherwise:
+    ///
+    /// - `torrents` and `server_state` only contain the fields that changed,
+    ///   which are merged into the known values. New torrents are added.
+    /// - `categories` contains added or changed categories, which replace the
+    ///   known ones with the same name.
+    /// - `tags` contains added tags.
+    /// - `trackers` contains trackers whose torrents changed, with the complete
+    ///   list of hashes of the torrents that have them now. It replaces the
+    ///   known list, so a tracker removed from only some torrents is reported
+    ///   here, not in `trackers_removed`.
+    /// - `torrents_removed`, `categories_removed` and `tags_removed` are
+    ///   hashes, category names and tags that no longer exist.
+    /// - `trackers_removed` are tracker URLs no torrent has anymore, which are
+    ///   removed for all torrents.
+    ///
+    /// Removing a category or a tag doesn't change the torrents that had it,
+    /// qBittorrent sends their updated `category` or `tags` separately.
+    pub fn apply(&mut self, data: SyncData) {
+        if data.full_update == Some(true) {
+            *self = Self::default();
+        }
+        self.rid = data.rid;
+
+        for (hash, torrent) in data.torrents.into_iter().flatten() {
+            match self.torrents.get_mut(&hash) {
+                Some(known) => merge(known, torrent),
+                None => {
+                    self.torrents.insert(hash, torrent);
+                }
+            }
+        }
+        for hash in data.torrents_removed.into_iter().flatten() {
+            self.torrents.remove(&hash);
+        }
+
+        self.categories.extend(data.categories.into_iter().flatten());
+        for name in data.categories_removed.into_iter().flatten() {
+            self.categories.remove(&name);
+        }
+
+        self.tags.extend(data.tags.into_iter().flatten());
+        for tag in data.tags_removed.into_iter().flatten() {
+            self.tags.remove(&tag);
+        }
+
+        self.trackers.extend(data.trackers.into_iter().flatten());
+        for url in data.trackers_removed.into_iter().flatten() {
+            self.trackers.remove(&url);
+        }
+
+        if let Some(server_state) = data.server_state {
+            merge(&mut self.server_state, server_state);
+        }
+    }
+}
+
+/// Overwrite the fields of `known` that are present in `update`
+fn merge<T: serde::Serialize + serde::de::DeserializeOwned>(known: &mut T, update: T) {
+    let (Ok(serde_json::Value::Object(mut merged)), Ok(serde_json::Value::Object(changed))) =
+        (serde_json::to_value(&*known), serde_json::to_value(&update))
+    else {
+        *known = update;
+        return;
+    };
+    merged.extend(changed.into_iter().filter(|(_, v)| !v.is_null()));
+    *known = serde_json::from_value(merged.into()).unwrap_or(update);
+}
+
+/// Global transfer info in [`SyncData`]. Partial updates only contain the
+/// fields that changed since last request.
+#[serde_as]
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, PartialEq)]
+pub struct ServerState {
+    /// Global download rate (bytes/s)
+    pub dl_info_speed: Option<u64>,
+    /// Data downloaded this session (bytes)
+    pub dl_info_data: Option<u64>,
+    /// Global upload rate (bytes/s)
+    pub up_info_speed: Option<u64>,
+    /// Data uploaded this session (bytes)
+    pub up_info_data: Option<u64>,
+    /// Download rate limit (bytes/s)
+    pub dl_rate_limit: Option<u64>,
+    /// Upload rate limit (bytes/s)
+    pub up_rate_limit: Option<u64>,
+    /// Data downloaded since qBittorrent was installed (bytes)
+    pub alltime_dl: Option<u64>,
+    /// Data uploaded since qBittorrent was installed (bytes)
+    pub alltime_ul: Option<u64>,
+    /// All-time share ratio. Sent as a string
+    #[serde_as(as = "Option<PickFirst<(_, DisplayFromStr)>>")]
+    #[serde(default)]
+    pub global_ratio: Option<f64>,
+    /// Number of connected peers across all torrents
+    pub total_peer_connections: Option<u64>,
+    /// DHT nodes connected to
+    pub dht_nodes: Option<u64>,
+    /// Connection status
+    pub connection_status: Option<ConnectionStatus>,
+    /// True if torrent queueing is enabled
+    pub queueing: Option<bool>,
+    /// True if alternative speed limits are enabled
+    pub use_alt_speed_limits: Option<bool>,
+    /// Transfer list refresh interval (milliseconds)
+    pub refresh_interval: Option<u64>,
+    /// Average time a disk job spends in the queue (milliseconds)
+    pub average_time_queue: Option<u64>,
+    /// Number of disk jobs in the queue
+    pub queued_io_jobs: Option<u64>,
+    /// Read cache hit rate (percentage). Sent as a string
+    #[serde_as(as = "Option<PickFirst<(_, DisplayFromStr)>>")]
+    #[serde(default)]
+    pub read_cache_hits: Option<f64>,
+    /// Read cache overload (percentage). Sent as a string
+    #[serde_as(as = "Option<PickFirst<(_, DisplayFromStr)>>")]
+    #[serde(default)]
+    pub read_cache_overload: Option<f64>,
+    /// Write cache overload (percentage). Sent as a string
+    #[serde_as(as = "Option<PickFirst<(_, DisplayFromStr)>>")]
+    #[serde(default)]
+    pub write_cache_overload: Option<f64>,
+    /// Total size of disk buffers (bytes)
+    pub total_buffers_size: Option<u64>,
+    /// Total size of data queued for disk writes (bytes)
+    pub total_queued_size: Option<u64>,
+    /// Other fields, as sent by the API
+    #[serde(flatten)]
+    pub other: HashMap<String, Value>,
+}
+
+/// Take the fields shared with [`TransferInfo`], so that it doesn't have to be
+/// requested separately when syncing. Missing fields default to `0` and
+/// [`ConnectionStatus::Unknown`], so convert a full update.
+impl From<&ServerState> for TransferInfo {
+    fn from(state: &ServerState) -> Self {
+        Self {
+            dl_info_speed: state.dl_info_speed.unwrap_or_default(),
+            dl_info_data: state.dl_info_data.unwrap_or_default(),
+            up_info_speed: state.up_info_speed.unwrap_or_default(),
+            up_info_data: state.up_info_data.unwrap_or_default(),
+            dl_rate_limit: state.dl_rate_limit.unwrap_or_default(),
+            up_rate_limit: state.up_rate_limit.unwrap_or_default(),
+            dht_nodes: state.dht_nodes.unwrap_or_default(),
+            connection_status: state
+                .connection_status
+                .clone()
+                .unwrap_or(ConnectionStatus::Unknown),
+            queueing: state.queueing,
+            use_alt_speed_limits: state.use_alt_speed_limits,
+            refresh_interval: state.refresh_interval,
+            last_external_address_v4: None,
+            last_external_address_v6: None,
+        }
+    }
+}
+
+/// Peers of a torrent. Peer keys that can't be parsed as a [`SocketAddr`], e.g.
+/// `host:port`, are kept as they are in `raw_peers` and `raw_peers_removed`
+/// instead of failing the whole response.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
+#[serde(from = "RawPeerSyncData", into = "RawPeerSyncData")]
 pub struct PeerSyncData {
     pub full_update: Option<bool>,
     pub peers: Option<HashMap<SocketAddr, Peer>>,
+    /// Peers whose key is not a valid socket address
+    pub raw_peers: Option<HashMap<String, Peer>>,
     pub peers_removed: Option<Vec<SocketAddr>>,
+    /// Removed peers whose key is not a valid socket address
+    pub raw_peers_removed: Option<Vec<String>>,
     pub rid: i64,
-    pub show_flags: bool,
+    /// Omitted in partial updates
+    pub show_flags: Option<bool>,
+}
+
+/// [`PeerSyncData`] as sent by the API, with peers keyed by string
+#[derive(serde::Serialize, serde::Deserialize)]
+struct RawPeerSyncData {
+    full_update: Option<bool>,
+    peers: Option<HashMap<String, Peer>>,
+    peers_removed: Option<Vec<String>>,
+    rid: i64,
+    show_flags: Option<bool>,
+}
+
+/// Parse a peer key, also accepting IPv6 addresses without brackets like
+/// `::1:6881`
+fn parse_peer_addr(key: &str) -> Option<SocketAddr> {
+    key.parse().ok().or_else(|| {
+        let (ip, port) = key.rsplit_once(':')?;
+        Some(SocketAddr::new(ip.parse::<IpAddr>().ok()?, port.parse().ok()?))
+    })
+}
+
+impl From<RawPeerSyncData> for PeerSyncData {
+    fn from(raw: RawPeerSyncData) -> Self {
+        let (peers, raw_peers) = match raw.peers {
+            Some(peers) => {
+                let (mut parsed, mut unparsed) = (HashMap::new(), HashMap::new());
+                for (key, peer) in peers {
+                    match parse_peer_addr(&key) {
+                        Some(addr) => parsed.insert(addr, peer),
+                        None => unparsed.insert(key, peer),
+                    };
+                }
+                (Some(parsed), (!unparsed.is_empty()).then_some(unparsed))
+            }
+            None => (None, None),
+        };
+        let (peers_removed, raw_peers_removed) = match raw.peers_removed {
+            Some(removed) => {
+                let (mut parsed, mut unparsed) = (Vec::new(), Vec::new());
+                for key in removed {
+                    match parse_peer_addr(&key) {
+                        Some(addr) => parsed.push(addr),
+                        None => unparsed.push(key),
+                    }
+                }
+                (Some(parsed), (!unparsed.is_empty()).then_some(unparsed))
+            }
+            None => (None, None),
+        };
+
+        Self {
+            full_update: raw.full_update,
+            peers,
+            raw_peers,
+            peers_removed,
+            raw_peers_removed,
+            rid: raw.rid,
+            show_flags: raw.show_flags,
+        }
+    }
+}
+
+impl From<PeerSyncData> for RawPeerSyncData {
+    fn from(data: PeerSyncData) -> Self {
+        let peers = match (data.peers, data.raw_peers) {
+            (None, None) => None,
+            (peers, raw_peers) => peers
+                .into_iter()
+                .flatten()
+                .map(|(addr, peer)| (addr.to_string(), peer))
+                .chain(raw_peers.into_iter().flatten())
+                .collect::<HashMap<_, _>>()
+                .pipe(Some),
+        };
+        let peers_removed = match (data.peers_removed, data.raw_peers_removed) {
+            (None, None) => None,
+            (removed, raw_removed) => removed
+                .into_iter()
+                .flatten()
+                .map(|addr| addr.to_string())
+                .chain(raw_removed.into_iter().flatten())
+                .collect::<Vec<_>>()
+                .pipe(Some),
+        };
+
+        Self {
+            full_update: data.full_update,
+            peers,
+            peers_removed,
+            rid: data.rid,
+            show_flags: data.show_flags,
+        }
+    }
 }
-#[derive(Debug, Clone, serde::Deserialize, PartialEq)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
 pub struct Peer {
     pub client: Option<String>,
     pub connection: Option<String>,
+    /// `None` unless `resolve_peer_countries` is enabled, see
+    /// [`Qbit::peer_countries_enabled`](crate::Qbit::peer_countries_enabled)
     pub country: Option<String>,
+    /// `None` unless `resolve_peer_countries` is enabled, see
+    /// [`Qbit::peer_countries_enabled`](crate::Qbit::peer_countries_enabled)
     pub country_code: Option<String>,
     pub dl_speed: Option<u64>,
     pub downloaded: Option<u64>,
@@ -57,3 +343,320 @@ pub struct Peer {
     pub up_speed: Option<u64>,
     pub uploaded: Option<u64>,
 }
+
+impl PeerSyncData {
+    /// Number of peers in this response
+    pub fn peer_count(&self) -> usize {
+        self.peers.as_ref().map_or(0, HashMap::len) + self.raw_peers.as_ref().map_or(0, HashMap::len)
+    }
+
+    /// Sum of the download speed of all peers in bytes/second. In a partial
+    /// update, peers without a changed `dl_speed` are not counted.
+    pub fn total_download_speed(&self) -> u64 {
+        self.iter_peers().filter_map(|peer| peer.dl_speed).sum()
+    }
+
+    /// Sum of the upload speed of all peers in bytes/second. In a partial
+    /// update, peers without a changed `up_speed` are not counted.
+    pub fn total_upload_speed(&self) -> u64 {
+        self.iter_peers().filter_map(|peer| peer.up_speed).sum()
+    }
+
+    /// Number of peers per country code. Peers without a country code, e.g.
+    /// when geolocation is disabled, are not counted.
+    pub fn peer_count_by_country(&self) -> HashMap<&str, usize> {
+        self.iter_peers()
+            .filter_map(|peer| peer.country_code.as_deref())
+            .fold(HashMap::new(), |mut counts, country| {
+                *counts.entry(country).or_default() += 1;
+                counts
+            })
+    }
+
+    fn iter_peers(&self) -> impl Iterator<Item = &Peer> {
+        self.peers
+            .iter()
+            .flat_map(HashMap::values)
+            .chain(self.raw_peers.iter().flat_map(HashMap::values))
+    }
+}
+
+#[test]
+fn test_peer_sync_data_totals() {
+    let peer = |country_code: Option<&str>, dl_speed, up_speed| Peer {
+        client: None,
+        connection: None,
+        country: None,
+        country_code: country_code.map(str::to_owned),
+        dl_speed,
+        downloaded: None,
+        files: None,
+        flags: None,
+        flags_desc: None,
+        ip: None,
+        port: None,
+        progress: None,
+        relevance: None,
+        up_speed,
+        uploaded: None,
+    };
+    let mut data = PeerSyncData {
+        full_update: Some(true),
+        peers: Some(HashMap::from([
+            ("1.1.1.1:6881".parse().unwrap(), peer(Some("us"), Some(100), Some(10))),
+            ("2.2.2.2:6881".parse().unwrap(), peer(Some("us"), Some(50), None)),
+            ("3.3.3.3:6881".parse().unwrap(), peer(Some("de"), None, Some(5))),
+            ("4.4.4.4:6881".parse().unwrap(), peer(None, Some(1), Some(1))),
+        ])),
+        raw_peers: None,
+        peers_removed: None,
+        raw_peers_removed: None,
+        rid: 1,
+        show_flags: Some(true),
+    };
+
+    assert_eq!(data.peer_count(), 4);
+    assert_eq!(data.total_download_speed(), 151);
+    assert_eq!(data.total_upload_speed(), 16);
+    assert_eq!(
+        data.peer_count_by_country(),
+        HashMap::from([("us", 2), ("de", 1)])
+    );
+
+    data.peers = None;
+    assert_eq!(data.peer_count(), 0);
+    assert_eq!(data.total_download_speed(), 0);
+    assert!(data.peer_count_by_country().is_empty());
+}
+
+#[test]
+fn test_peer_sync_data_raw_peers() {
+    let data: PeerSyncData = serde_json::from_value(serde_json::json!({
+        "full_update": true,
+        "peers": {
+            "1.2.3.4:6881": { "client": "a" },
+            "[2001:db8::1]:6881": { "client": "b" },
+            "2001:db8::2:6881": { "client": "c" },
+            "peer.i2p:6881": { "client": "d" },
+        },
+        "peers_removed": ["5.6.7.8:6881", "gone.i2p:6881"],
+        "rid": 1,
+        "show_flags": true,
+    }))
+    .unwrap();
+
+    let peers = data.peers.as_ref().unwrap();
+    assert_eq!(peers.len(), 3);
+    assert!(peers.contains_key(&"1.2.3.4:6881".parse().unwrap()));
+    assert!(peers.contains_key(&"[2001:db8::1]:6881".parse().unwrap()));
+    assert!(peers.contains_key(&"[2001:db8::2]:6881".parse().unwrap()));
+    assert_eq!(
+        data.raw_peers.as_ref().unwrap()["peer.i2p:6881"]
+            .client
+            .as_deref(),
+        Some("d")
+    );
+    assert_eq!(
+        data.peers_removed.as_deref(),
+        Some(&["5.6.7.8:6881".parse().unwrap()][..])
+    );
+    assert_eq!(
+        data.raw_peers_removed.as_deref(),
+        Some(&["gone.i2p:6881".to_owned()][..])
+    );
+    assert_eq!(data.peer_count(), 4);
+
+    let roundtrip: PeerSyncData =
+        serde_json::from_value(serde_json::to_value(&data).unwrap()).unwrap();
+    assert_eq!(roundtrip, data);
+}
+
+#[test]
+fn test_peer_sync_data_partial() {
+    let data: PeerSyncData = serde_json::from_value(serde_json::json!({
+        "peers": {
+            "1.2.3.4:6881": { "dl_speed": 100 },
+        },
+        "rid": 2,
+    }))
+    .unwrap();
+
+    assert_eq!(data.full_update, None);
+    assert_eq!(data.show_flags, None);
+    assert_eq!(data.rid, 2);
+    assert_eq!(data.total_download_speed(), 100);
+}
+
+#[test]
+fn test_server_state_transfer_info() {
+    let state: ServerState = serde_json::from_value(serde_json::json!({
+        "connection_status": "connected",
+        "dht_nodes": 300,
+        "dl_info_data": 2048,
+        "dl_info_speed": 1024,
+        "dl_rate_limit": 0,
+        "up_info_data": 4096,
+        "up_info_speed": 512,
+        "up_rate_limit": 100,
+        "queueing": true,
+        "free_space_on_disk": 1024,
+    }))
+    .unwrap();
+    assert!(state.other.contains_key("free_space_on_disk"));
+
+    assert_eq!(
+        TransferInfo::from(&state),
+        TransferInfo {
+            dl_info_speed: 1024,
+            dl_info_data: 2048,
+            up_info_speed: 512,
+            up_info_data: 4096,
+            dl_rate_limit: 0,
+            up_rate_limit: 100,
+            dht_nodes: 300,
+            connection_status: ConnectionStatus::Connected,
+            queueing: Some(true),
+            use_alt_speed_limits: None,
+            refresh_interval: None,
+            last_external_address_v4: None,
+            last_external_address_v6: None,
+        }
+    );
+}
+
+#[test]
+fn test_server_state_cache_stats() {
+    let state: ServerState = serde_json::from_value(serde_json::json!({
+        "average_time_queue": 12,
+        "queued_io_jobs": 3,
+        "read_cache_hits": "42.5",
+        "read_cache_overload": "0",
+        "write_cache_overload": 1,
+        "total_buffers_size": 1048576,
+        "total_queued_size": 0,
+    }))
+    .unwrap();
+
+    assert_eq!(state.average_time_queue, Some(12));
+    assert_eq!(state.queued_io_jobs, Some(3));
+    assert_eq!(state.read_cache_hits, Some(42.5));
+    assert_eq!(state.read_cache_overload, Some(0.0));
+    assert_eq!(state.write_cache_overload, Some(1.0));
+    assert_eq!(state.total_buffers_size, Some(1048576));
+    assert_eq!(state.total_queued_size, Some(0));
+    assert!(state.other.is_empty());
+}
+
+#[test]
+fn test_server_state_alltime() {
+    let state: ServerState = serde_json::from_value(serde_json::json!({
+        "alltime_dl": 5368709120u64,
+        "alltime_ul": 6603932221u64,
+        "global_ratio": "1.23",
+        "total_peer_connections": 17,
+    }))
+    .unwrap();
+
+    assert_eq!(state.alltime_dl, Some(5368709120));
+    assert_eq!(state.alltime_ul, Some(6603932221));
+    assert_eq!(state.global_ratio, Some(1.23));
+    assert_eq!(state.total_peer_connections, Some(17));
+    assert!(state.other.is_empty());
+
+    let json = serde_json::to_value(&state).unwrap();
+    assert_eq!(serde_json::from_value::<ServerState>(json).unwrap(), state);
+}
+
+#[cfg(test)]
+fn sync_data(value: serde_json::Value) -> SyncData {
+    serde_json::from_value(value).unwrap()
+}
+
+#[test]
+fn test_sync_state_categories_removed() {
+    let mut state = SyncState::default();
+    state.apply(sync_data(serde_json::json!({
+        "rid": 1,
+        "full_update": true,
+        "categories": {
+            "movies": { "name": "movies", "savePath": "/movies" },
+            "tv": { "name": "tv", "savePath": "/tv" },
+        },
+        "torrents": { "abc": { "name": "a", "category": "tv" } },
+    })));
+    state.apply(sync_data(serde_json::json!({
+        "rid": 2,
+        "categories_removed": ["tv"],
+    })));
+
+    assert_eq!(state.rid, 2);
+    assert_eq!(state.categories.keys().collect::<Vec<_>>(), ["movies"]);
+    // Left to the torrent's own update
+    assert_eq!(state.torrents["abc"].category.as_deref(), Some("tv"));
+}
+
+#[test]
+fn test_sync_state_tags_removed() {
+    let mut state = SyncState::default();
+    state.apply(sync_data(serde_json::json!({
+        "rid": 1,
+        "full_update": true,
+        "tags": ["a", "b"],
+    })));
+    state.apply(sync_data(serde_json::json!({
+        "rid": 2,
+        "tags": ["c"],
+        "tags_removed": ["a"],
+    })));
+
+    assert_eq!(state.tags, HashSet::from(["b".to_owned(), "c".to_owned()]));
+}
+
+#[test]
+fn test_sync_state_trackers_removed() {
+    let mut state = SyncState::default();
+    state.apply(sync_data(serde_json::json!({
+        "rid": 1,
+        "full_update": true,
+        "trackers": {
+            "http://a.example/announce": ["abc", "def"],
+            "http://b.example/announce": ["abc"],
+        },
+    })));
+
+    // Removed from one torrent only: the tracker's list is replaced
+    state.apply(sync_data(serde_json::json!({
+        "rid": 2,
+        "trackers": { "http://a.example/announce": ["def"] },
+    })));
+    assert_eq!(state.trackers["http://a.example/announce"], ["def"]);
+    assert_eq!(state.trackers["http://b.example/announce"], ["abc"]);
+
+    // Removed from every torrent
+    state.apply(sync_data(serde_json::json!({
+        "rid": 3,
+        "trackers_removed": ["http://b.example/announce"],
+    })));
+    assert_eq!(
+        state.trackers.keys().collect::<Vec<_>>(),
+        ["http://a.example/announce"]
+    );
+}
+
+#[test]
+fn test_sync_state_partial_torrent() {
+    let mut state = SyncState::default();
+    state.apply(sync_data(serde_json::json!({
+        "rid": 1,
+        "full_update": true,
+        "torrents": { "abc": { "name": "a", "progress": 0.5 } },
+    })));
+    state.apply(sync_data(serde_json::json!({
+        "rid": 2,
+        "torrents": { "abc": { "progress": 1.0 } },
+    })));
+
+    let torrent = &state.torrents["abc"];
+    assert_eq!(torrent.name.as_deref(), Some("a"));
+    assert_eq!(torrent.progress, Some(1.0));
+}
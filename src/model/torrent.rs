@@ -1,10 +1,16 @@
-use std::fmt::{Debug, Display};
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display},
+    path::Path,
+    str::FromStr,
+    time::Duration,
+};
 
 use reqwest::Url;
-use serde::Serialize;
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_with::{skip_serializing_none, SerializeDisplay};
 
-use crate::model::Sep;
+use crate::model::{ParseEnumError, Sep, Tracker, UrlOrRaw};
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -22,7 +28,61 @@ pub enum TorrentFilter {
     Errored,
 }
 
-#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+impl TorrentFilter {
+    /// All filters, in the order they are listed in the API documentation
+    pub const VARIANTS: &'static [Self] = &[
+        Self::All,
+        Self::Downloading,
+        Self::Completed,
+        Self::Paused,
+        Self::Active,
+        Self::Inactive,
+        Self::Resumed,
+        Self::Stalled,
+        Self::StalledUploading,
+        Self::StalledDownloading,
+        Self::Errored,
+    ];
+
+    /// Name of the filter used by the API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::All => "all",
+            Self::Downloading => "downloading",
+            Self::Completed => "completed",
+            Self::Paused => "paused",
+            Self::Active => "active",
+            Self::Inactive => "inactive",
+            Self::Resumed => "resumed",
+            Self::Stalled => "stalled",
+            Self::StalledUploading => "stalledUploading",
+            Self::StalledDownloading => "stalledDownloading",
+            Self::Errored => "errored",
+        }
+    }
+}
+
+impl Display for TorrentFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TorrentFilter {
+    type Err = ParseEnumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::VARIANTS
+            .iter()
+            .find(|filter| filter.as_str() == s)
+            .cloned()
+            .ok_or_else(|| {
+                ParseEnumError::new("torrent filter", s, Self::VARIANTS.iter().map(Self::as_str))
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, serde::Serialize, serde::Deserialize)]
 pub struct Torrent {
     /// Time (Unix Epoch) when the torrent was added to the client
     pub added_on: Option<i64>,
@@ -49,7 +109,8 @@ pub struct Torrent {
     pub downloaded: Option<i64>,
     /// Amount of data downloaded this session
     pub downloaded_session: Option<i64>,
-    /// Torrent ETA (seconds)
+    /// Torrent ETA (seconds). [`Torrent::INFINITE_ETA`] is used when the ETA
+    /// is unknown or effectively infinite, see [`Torrent::eta_duration`].
     pub eta: Option<i64>,
     /// True if first last piece are prioritized
     pub f_l_piece_prio: Option<bool>,
@@ -93,6 +154,9 @@ pub struct Torrent {
     /// is enabled, the value is -2. And if max_seeding_time is unset it
     /// have a default value -1.
     pub seeding_time_limit: Option<i64>,
+    /// Per torrent inactive seeding time limit (minutes), with the same
+    /// special values as `seeding_time_limit`. For API ≥ v2.9.2
+    pub inactive_seeding_time_limit: Option<i64>,
     /// Time (Unix Epoch) when this torrent was last seen complete
     pub seen_complete: Option<i64>,
     /// True if sequential download is enabled
@@ -121,6 +185,206 @@ pub struct Torrent {
     pub uploaded_session: Option<i64>,
     /// Torrent upload speed (bytes/:,)
     pub upspeed: Option<i64>,
+    /// Trackers of the torrent, only present when requested with
+    /// [`GetTorrentListArg::include_trackers`]
+    pub trackers: Option<Vec<Tracker>>,
+}
+
+impl Torrent {
+    /// Sentinel `eta` value (100 days) reported by qBittorrent when the ETA is
+    /// unknown or effectively infinite, e.g. for stalled torrents.
+    pub const INFINITE_ETA: i64 = 8_640_000;
+
+    /// ETA of the torrent, or `None` if it's absent, negative or
+    /// [`Torrent::INFINITE_ETA`].
+    pub fn eta_duration(&self) -> Option<Duration> {
+        match self.eta? {
+            eta if (0..Self::INFINITE_ETA).contains(&eta) => Some(Duration::from_secs(eta as u64)),
+            _ => None,
+        }
+    }
+
+    /// Download speed limit (bytes/s), or `None` if unlimited or absent.
+    /// Unlimited is reported as `-1`, or as `0` by some versions.
+    pub fn download_limit(&self) -> Option<u64> {
+        self.dl_limit.filter(|limit| *limit > 0).map(|limit| limit as u64)
+    }
+
+    /// Upload speed limit (bytes/s), or `None` if unlimited or absent, see
+    /// [`Torrent::download_limit`].
+    pub fn upload_limit(&self) -> Option<u64> {
+        self.up_limit.filter(|limit| *limit > 0).map(|limit| limit as u64)
+    }
+
+    /// Maximum share ratio in effect for this torrent, or `None` if unlimited
+    /// or absent.
+    pub fn max_share_ratio(&self) -> Option<f64> {
+        self.max_ratio.filter(|ratio| *ratio >= 0.0)
+    }
+
+    /// Per torrent share ratio limit, see [`RatioLimit`].
+    pub fn share_ratio_limit(&self) -> Option<RatioLimit> {
+        self.ratio_limit.map(RatioLimit::from)
+    }
+
+    /// Whether every piece is available from connected peers. qBittorrent
+    /// reports an availability of `-1` for torrents that are not downloading,
+    /// which counts as not available.
+    pub fn is_fully_available(&self) -> bool {
+        self.availability.is_some_and(|availability| availability >= 1.0)
+    }
+
+    /// Classify the swarm of an incomplete torrent, see [`SwarmHealth`]. Seeds
+    /// are counted from both the connected seeds and the seeds reported by
+    /// trackers.
+    pub fn swarm_health(&self) -> SwarmHealth {
+        let seeds = self
+            .num_seeds
+            .into_iter()
+            .chain(self.num_complete)
+            .max()
+            .unwrap_or_default();
+        match (self.is_fully_available(), seeds > 0) {
+            (true, true) => SwarmHealth::Healthy,
+            (false, false) => SwarmHealth::Dead,
+            _ => SwarmHealth::Degraded,
+        }
+    }
+
+    /// The first tracker with working status, or `None` if no tracker is
+    /// working.
+    pub fn working_tracker(&self) -> Option<&str> {
+        self.tracker.as_deref().filter(|tracker| !tracker.is_empty())
+    }
+
+    /// Host of [`Torrent::working_tracker`], e.g. `tracker.example.org` for
+    /// `https://tracker.example.org/announce?passkey=...`. Useful for grouping
+    /// torrents by tracker. Returns `None` if no tracker is working or its URL
+    /// can't be parsed.
+    pub fn primary_tracker_host(&self) -> Option<String> {
+        Url::parse(self.working_tracker()?)
+            .ok()?
+            .host_str()
+            .map(str::to_owned)
+    }
+
+    /// Tags of the torrent, parsed from the comma-concatenated `tags` field.
+    pub fn tag_list(&self) -> impl Iterator<Item = &str> {
+        self.tags
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+    }
+
+    /// Path of the torrent's data, i.e. [`Torrent::content_path`]. Unlike
+    /// [`Torrent::save_path`], which is the directory the torrent is saved
+    /// under, this is the file itself for a single-file torrent and the root
+    /// folder of the files for a multi-file torrent:
+    ///
+    /// | Torrent     | `save_path`        | `data_root`                     |
+    /// |-------------|--------------------|---------------------------------|
+    /// | Single-file | `/downloads`       | `/downloads/movie.mkv`          |
+    /// | Multi-file  | `/downloads`       | `/downloads/Show S01`           |
+    ///
+    /// Hand this to a player or a media scanner, and use its parent for "open
+    /// containing folder" on a single-file torrent. Returns `None` if the
+    /// field is absent or empty.
+    pub fn data_root(&self) -> Option<&Path> {
+        self.content_path
+            .as_deref()
+            .filter(|path| !path.is_empty())
+            .map(Path::new)
+    }
+}
+
+/// Health of a torrent's swarm, see [`Torrent::swarm_health`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SwarmHealth {
+    /// Every piece is available and there are seeds
+    Healthy,
+    /// Either every piece is available but only spread across leechers, or
+    /// there are seeds but not all pieces are available from connected peers
+    Degraded,
+    /// No seeds and not all pieces are available, the torrent can't complete
+    Dead,
+}
+
+/// Subset of [`Torrent`], returned by
+/// [`Qbit::get_torrent_list_minimal`](crate::Qbit::get_torrent_list_minimal).
+/// Other fields are skipped while deserializing instead of being allocated,
+/// which is cheaper for large torrent lists.
+#[derive(Debug, Clone, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+pub struct TorrentSummary {
+    /// Torrent hash
+    pub hash: Option<String>,
+    /// Torrent name
+    pub name: Option<String>,
+    /// Torrent state
+    pub state: Option<State>,
+    /// Torrent progress (percentage/100)
+    pub progress: Option<f64>,
+}
+
+/// Keep torrents in any of `categories` that have any of `tags`. An empty
+/// slice doesn't filter at all, and an empty string matches torrents without a
+/// category or tag, like [`group_torrents_by_category`] and
+/// [`group_torrents_by_tag`].
+pub fn filter_torrents(torrents: Vec<Torrent>, categories: &[String], tags: &[String]) -> Vec<Torrent> {
+    torrents
+        .into_iter()
+        .filter(|torrent| {
+            categories.is_empty()
+                || categories
+                    .iter()
+                    .any(|c| c == torrent.category.as_deref().unwrap_or_default())
+        })
+        .filter(|torrent| {
+            let mut tag_list = torrent.tag_list().peekable();
+            tags.is_empty()
+                || match tag_list.peek() {
+                    None => tags.iter().any(String::is_empty),
+                    Some(_) => tag_list.any(|tag| tags.iter().any(|t| t == tag)),
+                }
+        })
+        .collect()
+}
+
+/// Group torrents by category. Torrents without a category are grouped under
+/// an empty string, like the "Uncategorized" filter of qBittorrent.
+pub fn group_torrents_by_category(torrents: Vec<Torrent>) -> HashMap<String, Vec<Torrent>> {
+    torrents
+        .into_iter()
+        .fold(HashMap::new(), |mut groups, torrent| {
+            groups
+                .entry(torrent.category.clone().unwrap_or_default())
+                .or_insert_with(Vec::new)
+                .push(torrent);
+            groups
+        })
+}
+
+/// Group torrents by tag. A torrent with several tags appears under each of
+/// them, and torrents without tags are grouped under an empty string, like the
+/// "Untagged" filter of qBittorrent.
+pub fn group_torrents_by_tag(torrents: Vec<Torrent>) -> HashMap<String, Vec<Torrent>> {
+    torrents
+        .into_iter()
+        .fold(HashMap::new(), |mut groups, torrent| {
+            let tags = torrent
+                .tag_list()
+                .map(str::to_owned)
+                .collect::<Vec<_>>();
+            if tags.is_empty() {
+                groups.entry(String::new()).or_insert_with(Vec::new).push(torrent);
+            } else {
+                for tag in tags {
+                    groups.entry(tag).or_insert_with(Vec::new).push(torrent.clone());
+                }
+            }
+            groups
+        })
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -186,7 +450,121 @@ pub enum State {
     Unknown,
 }
 
-#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+impl State {
+    /// All states
+    pub const VARIANTS: &'static [Self] = &[
+        Self::Error,
+        Self::MissingFiles,
+        Self::Uploading,
+        Self::PausedUP,
+        Self::QueuedUP,
+        Self::StalledUP,
+        Self::CheckingUP,
+        Self::ForcedUP,
+        Self::Allocating,
+        Self::Downloading,
+        Self::MetaDL,
+        Self::PausedDL,
+        Self::QueuedDL,
+        Self::StalledDL,
+        Self::CheckingDL,
+        Self::ForcedDL,
+        Self::CheckingResumeData,
+        Self::Moving,
+        Self::Unknown,
+    ];
+
+    /// Name of the state used by the API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::MissingFiles => "missingFiles",
+            Self::Uploading => "uploading",
+            Self::PausedUP => "pausedUP",
+            Self::QueuedUP => "queuedUP",
+            Self::StalledUP => "stalledUP",
+            Self::CheckingUP => "checkingUP",
+            Self::ForcedUP => "forcedUP",
+            Self::Allocating => "allocating",
+            Self::Downloading => "downloading",
+            Self::MetaDL => "metaDL",
+            Self::PausedDL => "pausedDL",
+            Self::QueuedDL => "queuedDL",
+            Self::StalledDL => "stalledDL",
+            Self::CheckingDL => "checkingDL",
+            Self::ForcedDL => "forcedDL",
+            Self::CheckingResumeData => "checkingResumeData",
+            Self::Moving => "moving",
+            Self::Unknown => "unknown",
+        }
+    }
+
+    /// Lifecycle category of the state, e.g. to color torrents by
+    pub fn category(&self) -> StateCategory {
+        match self {
+            Self::Allocating
+            | Self::Downloading
+            | Self::MetaDL
+            | Self::StalledDL
+            | Self::ForcedDL => StateCategory::Downloading,
+            Self::Uploading | Self::StalledUP | Self::ForcedUP => StateCategory::Seeding,
+            Self::PausedUP | Self::PausedDL => StateCategory::Paused,
+            Self::CheckingUP | Self::CheckingDL | Self::CheckingResumeData => {
+                StateCategory::Checking
+            }
+            Self::Error | Self::MissingFiles => StateCategory::Errored,
+            Self::QueuedUP | Self::QueuedDL => StateCategory::Queued,
+            Self::Moving | Self::Unknown => StateCategory::Other,
+        }
+    }
+}
+
+/// High-level lifecycle category of a [`State`], see [`State::category`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StateCategory {
+    /// Downloading, including fetching metadata, allocating disk space and
+    /// stalled or forced downloads
+    Downloading,
+    /// Seeding, including stalled or forced uploads
+    Seeding,
+    /// Paused (stopped in qBittorrent 5+), whether finished or not
+    Paused,
+    /// Checking data, including resume data on startup
+    Checking,
+    /// Errored or missing files
+    Errored,
+    /// Queued for download or upload
+    Queued,
+    /// Moving or unknown
+    Other,
+}
+
+impl Display for State {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for State {
+    type Err = ParseEnumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            // Names used by qBittorrent 5+
+            "stoppedUP" => Ok(Self::PausedUP),
+            "stoppedDL" => Ok(Self::PausedDL),
+            _ => Self::VARIANTS
+                .iter()
+                .find(|state| state.as_str() == s)
+                .cloned()
+                .ok_or_else(|| {
+                    ParseEnumError::new("torrent state", s, Self::VARIANTS.iter().map(Self::as_str))
+                }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct TorrentProperty {
     /// Torrent save path
     pub save_path: Option<String>,
@@ -256,15 +634,27 @@ pub struct TorrentProperty {
     pub up_speed: Option<i64>,
 }
 
+impl TorrentProperty {
+    /// Whether the next announce is due within `min_interval`, so that
+    /// reannouncing now moves it forward by at most `min_interval`. `false` if
+    /// the countdown is absent.
+    pub fn reannounce_due(&self, min_interval: Duration) -> bool {
+        self.reannounce
+            .is_some_and(|secs| secs <= min_interval.as_secs() as i64)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct WebSeed {
-    /// Web seed URL
-    pub url: Url,
+    /// Web seed URL. Kept as is if it's malformed, so that it doesn't fail
+    /// the whole list
+    pub url: UrlOrRaw,
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct TorrentContent {
-    /// File index
+    /// File index, used to identify the file in
+    /// [`Qbit::set_file_priority`](crate::Qbit::set_file_priority)
     pub index: u64,
     /// File name (including relative path),
     pub name: String,
@@ -285,6 +675,8 @@ pub struct TorrentContent {
     pub availability: f64,
 }
 
+/// File priority. The encoding is not contiguous: `2`, `3` and `5` are not
+/// used, see [`Priority::from_u8`].
 #[derive(
     Debug,
     Clone,
@@ -310,6 +702,120 @@ pub enum Priority {
     Maximal       = 7,
 }
 
+impl Priority {
+    /// Priority encoded as `value`, or `None` for values that don't map to a
+    /// priority, including the unused `2`, `3` and `5`.
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::DoNotDownload),
+            1 => Some(Self::Normal),
+            4 => Some(Self::Mixed),
+            6 => Some(Self::High),
+            7 => Some(Self::Maximal),
+            _ => None,
+        }
+    }
+
+    /// Value used by the API to represent this priority
+    pub fn as_u8(self) -> u8 {
+        self as u8
+    }
+}
+
+/// Files of a torrent nested by directory, see [`build_file_tree`]. The root
+/// directory has an empty name.
+pub type FileTree = Directory;
+
+/// Directory in a [`FileTree`]
+#[derive(Debug, Clone, PartialEq, Default, serde::Serialize)]
+pub struct Directory {
+    /// Directory name, without its parent directories
+    pub name: String,
+    /// Subdirectories, in the order they first appear in the contents
+    pub children: Vec<Directory>,
+    /// Files directly in this directory
+    pub files: Vec<FileNode>,
+}
+
+impl Directory {
+    /// Total size of all files in this directory and its subdirectories
+    /// (bytes)
+    pub fn size(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum::<u64>()
+            + self.children.iter().map(Directory::size).sum::<u64>()
+    }
+}
+
+/// File in a [`FileTree`]
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct FileNode {
+    /// File name, without its parent directories
+    pub name: String,
+    /// Same as [`TorrentContent::index`]
+    pub index: u64,
+    /// File size (bytes)
+    pub size: u64,
+    /// File progress (percentage/100)
+    pub progress: f64,
+    /// File priority
+    pub priority: Priority,
+}
+
+/// Availability of a whole torrent from its contents, as the average of the
+/// availability of each file weighted by its size. Returns `0.0` if the
+/// contents are empty or have no size.
+pub fn content_availability(contents: &[TorrentContent]) -> f64 {
+    let total = contents.iter().map(|content| content.size).sum::<u64>();
+    if total == 0 {
+        return 0.0;
+    }
+
+    contents
+        .iter()
+        .map(|content| content.availability * content.size as f64)
+        .sum::<f64>()
+        / total as f64
+}
+
+/// Nest the contents of a torrent into a tree by splitting their names on
+/// `/`. Empty path components, e.g. from a trailing or doubled slash, are
+/// ignored.
+pub fn build_file_tree(contents: &[TorrentContent]) -> FileTree {
+    let mut root = FileTree::default();
+    for content in contents {
+        let mut components = content
+            .name
+            .split('/')
+            .filter(|component| !component.is_empty())
+            .collect::<Vec<_>>();
+        let Some(name) = components.pop() else {
+            continue;
+        };
+
+        let dir = components.into_iter().fold(&mut root, |dir, component| {
+            let pos = match dir.children.iter().position(|child| child.name == component) {
+                Some(pos) => pos,
+                None => {
+                    dir.children.push(Directory {
+                        name: component.to_owned(),
+                        ..Directory::default()
+                    });
+                    dir.children.len() - 1
+                }
+            };
+            &mut dir.children[pos]
+        });
+        dir.files.push(FileNode {
+            name: name.to_owned(),
+            index: content.index,
+            size: content.size,
+            progress: content.progress,
+            priority: content.priority,
+        });
+    }
+    root
+}
+
 #[derive(
     Debug,
     Clone,
@@ -334,12 +840,26 @@ pub enum PieceState {
 /// `|` separeated list of hash values or `all`
 #[derive(Debug, Clone, PartialEq, Eq, SerializeDisplay)]
 pub enum Hashes {
-    /// A list of torrent hashes separated by `|`
+    /// A list of torrent hashes separated by `|`. Must not be empty, see
+    /// [`Hashes::is_empty`]
     Hashes(Sep<String, '|'>),
     /// All torrents
     All,
 }
 
+impl Hashes {
+    /// Whether no torrent is selected. Unlike [`Hashes::All`], an empty list
+    /// is sent as an empty `hashes`, which qBittorrent treats as no torrent,
+    /// so methods taking [`Hashes`] reject it with
+    /// [`Error::EmptyInput`](crate::Error::EmptyInput).
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Hashes::Hashes(hashes) => hashes.is_empty(),
+            Hashes::All => false,
+        }
+    }
+}
+
 impl<V: Into<Vec<String>>> From<V> for Hashes {
     fn from(hashes: V) -> Self {
         Hashes::Hashes(Sep::from(hashes))
@@ -367,6 +887,10 @@ pub struct GetTorrentListArg {
     /// `downloading`, `seeding`, `completed`, `paused`, `active`, `inactive`,
     /// `resumed`, `stalled`, `stalled_uploading`, `stalled_downloading`,
     /// `errored`
+    ///
+    /// `None` leaves the parameter out, which qBittorrent treats the same as
+    /// [`TorrentFilter::All`]: no torrent is filtered out by state. Use
+    /// [`GetTorrentListArg::everything`] to send `all` explicitly.
     pub filter: Option<TorrentFilter>,
     /// Get torrents with the given category (empty string means "without category"; no "category" parameter means "any category" <- broken until [#11748](https://github.com/qbittorrent/qBittorrent/issues/11748) is resolved). Remember to URL-encode the category name. For example, `My category` becomes `My%20category`
     pub category: Option<String>,
@@ -376,6 +900,9 @@ pub struct GetTorrentListArg {
     pub tag: Option<String>,
     /// Sort torrents by given key. They can be sorted using any field of the
     /// response's JSON array (which are documented below) as the sort key.
+    /// Prefer a [`TorrentSortKey`], which converts into a `String`, to avoid
+    /// typos that silently leave the list unsorted.
+    #[cfg_attr(feature = "builder", builder(setter(into)))]
     pub sort: Option<String>,
     /// Enable reverse sorting. Defaults to `false`
     pub reverse: Option<bool>,
@@ -385,6 +912,192 @@ pub struct GetTorrentListArg {
     pub offset: Option<i64>,
     /// Filter by hashes. Can contain multiple hashes separated by `\|`
     pub hashes: Option<String>,
+    /// Embed the trackers of each torrent in [`Torrent::trackers`]. For
+    /// qBittorrent ≥ v5.1, older versions make
+    /// [`Qbit::get_torrent_list`](crate::Qbit::get_torrent_list) fail with
+    /// [`ApiError::EndpointUnavailable`](crate::ApiError::EndpointUnavailable).
+    #[serde(rename = "includeTrackers")]
+    pub include_trackers: Option<bool>,
+}
+
+impl GetTorrentListArg {
+    /// Same as [`GetTorrentListArg::default`], but sends `filter=all`
+    /// explicitly instead of leaving the parameter out.
+    pub fn everything() -> Self {
+        Self {
+            filter: Some(TorrentFilter::All),
+            ..Default::default()
+        }
+    }
+}
+
+/// Field of [`Torrent`] to sort the torrent list by, see
+/// [`GetTorrentListArg::sort`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TorrentSortKey {
+    AddedOn,
+    AmountLeft,
+    AutoTmm,
+    Availability,
+    Category,
+    Completed,
+    CompletionOn,
+    ContentPath,
+    DlLimit,
+    Dlspeed,
+    Downloaded,
+    DownloadedSession,
+    Eta,
+    FLPiecePrio,
+    ForceStart,
+    Hash,
+    LastActivity,
+    MagnetUri,
+    MaxRatio,
+    MaxSeedingTime,
+    Name,
+    NumComplete,
+    NumIncomplete,
+    NumLeechs,
+    NumSeeds,
+    Priority,
+    Progress,
+    Ratio,
+    RatioLimit,
+    SavePath,
+    SeedingTime,
+    SeedingTimeLimit,
+    InactiveSeedingTimeLimit,
+    SeenComplete,
+    SeqDl,
+    Size,
+    State,
+    SuperSeeding,
+    Tags,
+    TimeActive,
+    TotalSize,
+    Tracker,
+    UpLimit,
+    Uploaded,
+    UploadedSession,
+    Upspeed,
+}
+
+impl TorrentSortKey {
+    /// All keys, in the order of the fields of [`Torrent`]
+    pub const VARIANTS: &'static [Self] = &[
+        Self::AddedOn,
+        Self::AmountLeft,
+        Self::AutoTmm,
+        Self::Availability,
+        Self::Category,
+        Self::Completed,
+        Self::CompletionOn,
+        Self::ContentPath,
+        Self::DlLimit,
+        Self::Dlspeed,
+        Self::Downloaded,
+        Self::DownloadedSession,
+        Self::Eta,
+        Self::FLPiecePrio,
+        Self::ForceStart,
+        Self::Hash,
+        Self::LastActivity,
+        Self::MagnetUri,
+        Self::MaxRatio,
+        Self::MaxSeedingTime,
+        Self::Name,
+        Self::NumComplete,
+        Self::NumIncomplete,
+        Self::NumLeechs,
+        Self::NumSeeds,
+        Self::Priority,
+        Self::Progress,
+        Self::Ratio,
+        Self::RatioLimit,
+        Self::SavePath,
+        Self::SeedingTime,
+        Self::SeedingTimeLimit,
+        Self::InactiveSeedingTimeLimit,
+        Self::SeenComplete,
+        Self::SeqDl,
+        Self::Size,
+        Self::State,
+        Self::SuperSeeding,
+        Self::Tags,
+        Self::TimeActive,
+        Self::TotalSize,
+        Self::Tracker,
+        Self::UpLimit,
+        Self::Uploaded,
+        Self::UploadedSession,
+        Self::Upspeed,
+    ];
+
+    /// Name of the field used by the API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AddedOn => "added_on",
+            Self::AmountLeft => "amount_left",
+            Self::AutoTmm => "auto_tmm",
+            Self::Availability => "availability",
+            Self::Category => "category",
+            Self::Completed => "completed",
+            Self::CompletionOn => "completion_on",
+            Self::ContentPath => "content_path",
+            Self::DlLimit => "dl_limit",
+            Self::Dlspeed => "dlspeed",
+            Self::Downloaded => "downloaded",
+            Self::DownloadedSession => "downloaded_session",
+            Self::Eta => "eta",
+            Self::FLPiecePrio => "f_l_piece_prio",
+            Self::ForceStart => "force_start",
+            Self::Hash => "hash",
+            Self::LastActivity => "last_activity",
+            Self::MagnetUri => "magnet_uri",
+            Self::MaxRatio => "max_ratio",
+            Self::MaxSeedingTime => "max_seeding_time",
+            Self::Name => "name",
+            Self::NumComplete => "num_complete",
+            Self::NumIncomplete => "num_incomplete",
+            Self::NumLeechs => "num_leechs",
+            Self::NumSeeds => "num_seeds",
+            Self::Priority => "priority",
+            Self::Progress => "progress",
+            Self::Ratio => "ratio",
+            Self::RatioLimit => "ratio_limit",
+            Self::SavePath => "save_path",
+            Self::SeedingTime => "seeding_time",
+            Self::SeedingTimeLimit => "seeding_time_limit",
+            Self::InactiveSeedingTimeLimit => "inactive_seeding_time_limit",
+            Self::SeenComplete => "seen_complete",
+            Self::SeqDl => "seq_dl",
+            Self::Size => "size",
+            Self::State => "state",
+            Self::SuperSeeding => "super_seeding",
+            Self::Tags => "tags",
+            Self::TimeActive => "time_active",
+            Self::TotalSize => "total_size",
+            Self::Tracker => "tracker",
+            Self::UpLimit => "up_limit",
+            Self::Uploaded => "uploaded",
+            Self::UploadedSession => "uploaded_session",
+            Self::Upspeed => "upspeed",
+        }
+    }
+}
+
+impl Display for TorrentSortKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<TorrentSortKey> for String {
+    fn from(key: TorrentSortKey) -> Self {
+        key.as_str().to_owned()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
@@ -401,6 +1114,20 @@ pub struct TorrentFile {
     pub filename: String,
     pub data: Vec<u8>
 }
+impl TorrentSource {
+    /// Whether there's no URL or torrent file to add, which qBittorrent
+    /// rejects. This is the case of [`TorrentSource::default`].
+    pub fn is_empty(&self) -> bool {
+        match self {
+            TorrentSource::Urls { urls } => urls.is_empty(),
+            TorrentSource::TorrentFiles { torrents } => torrents.is_empty(),
+        }
+    }
+}
+
+/// No URL at all, so that [`AddTorrentArg`] can derive `Default`. Always set
+/// the source, [`Qbit::add_torrent`](crate::Qbit::add_torrent) rejects an
+/// empty one.
 impl Default for TorrentSource {
     fn default() -> Self {
         TorrentSource::Urls {
@@ -419,6 +1146,9 @@ fn is_torrent_files(source: &TorrentSource) -> bool {
 #[derive(Debug, Clone, PartialEq, serde::Serialize, Default)]
 #[skip_serializing_none]
 pub struct AddTorrentArg {
+    /// URLs or torrent files to add. Mandatory: the builder requires it, and
+    /// the empty default is rejected with
+    /// [`Error::EmptyInput`](crate::Error::EmptyInput)
     #[serde(flatten)]
     #[cfg_attr(feature = "builder", builder(!default, setter(!strip_option)))]
     #[serde(skip_serializing_if = "is_torrent_files")]
@@ -426,6 +1156,15 @@ pub struct AddTorrentArg {
     #[serde(skip_serializing_if = "Option::is_none")]
     /// Download folder
     pub savepath: Option<String>,
+    /// Folder for incomplete downloads, moved to `savepath` once completed.
+    /// For qBittorrent ≥ 4.4
+    #[serde(rename = "downloadPath")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_path: Option<String>,
+    /// Whether `download_path` should be used. For qBittorrent ≥ 4.4
+    #[serde(rename = "useDownloadPath")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub use_download_path: Option<bool>,
     /// Cookie sent to download the .torrent file
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cookie: Option<String>,
@@ -446,6 +1185,18 @@ pub struct AddTorrentArg {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub paused: Option<String>,
 
+    /// Add torrents in the stopped state, replaces `paused` in qBittorrent ≥
+    /// v5, which ignores it. Possible values are `true`, `false` (default)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stopped: Option<String>,
+
+    /// Stop the torrent once the condition is met. Possible values are
+    /// `None` (default), `MetadataReceived` and `FilesChecked`. For
+    /// qBittorrent ≥ 4.5 (Web API ≥ 2.8.15)
+    #[serde(rename = "stopCondition")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_condition: Option<String>,
+
     /// Create the root folder. Possible values are `true`, `false`, unset
     /// (default)
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -468,12 +1219,12 @@ pub struct AddTorrentArg {
     /// Set torrent share ratio limit
     #[serde(rename = "ratioLimit")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub ratio_limit: Option<f64>,
+    pub ratio_limit: Option<RatioLimit>,
 
-    /// Set torrent seeding time limit. Unit in minutes
+    /// Set torrent seeding time limit
     #[serde(rename = "seedingTimeLimit")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub seeding_time_limit: Option<i64>,
+    pub seeding_time_limit: Option<SeedingTimeLimit>,
 
     /// Whether Automatic Torrent Management should be used
     #[serde(rename = "autoTMM")]
@@ -493,6 +1244,106 @@ pub struct AddTorrentArg {
     pub first_last_piece_priority: Option<String>,
 }
 
+/// Everything about a torrent, see
+/// [`Qbit::get_torrent_detail`](crate::Qbit::get_torrent_detail). Parts that
+/// qBittorrent reports as not found, e.g. when the torrent is removed while
+/// they're fetched, are `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TorrentDetail {
+    /// Entry of the torrent in the torrent list
+    pub torrent: Torrent,
+    pub properties: Option<TorrentProperty>,
+    pub trackers: Option<Vec<Tracker>>,
+    pub contents: Option<Vec<TorrentContent>>,
+}
+
+/// Page of the torrent list returned by
+/// [`Qbit::get_torrent_page`](crate::Qbit::get_torrent_page)
+#[derive(Debug, Clone, PartialEq)]
+pub struct TorrentPage {
+    /// Torrents on the page
+    pub items: Vec<Torrent>,
+    /// Number of torrents matching the filters, across all pages. `None` if
+    /// it wasn't counted, see `with_total`
+    pub total: Option<u64>,
+}
+
+/// Outcome of [`Qbit::add_torrent_checked`](crate::Qbit::add_torrent_checked)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddOutcome {
+    /// Hashes of the torrents that were not present before
+    Added(Vec<String>),
+    /// No new torrent was found, qBittorrent ignores torrents that are already
+    /// present
+    AlreadyPresent,
+}
+
+impl AddOutcome {
+    pub(crate) fn from_added(added: Vec<String>) -> Self {
+        if added.is_empty() {
+            Self::AlreadyPresent
+        } else {
+            Self::Added(added)
+        }
+    }
+}
+
+/// Info hash (v1) of a magnet link as lowercase hex, like [`Torrent::hash`].
+/// Returns `None` for other URLs and magnet links without a `urn:btih:` topic.
+pub fn magnet_info_hash(url: &Url) -> Option<String> {
+    if url.scheme() != "magnet" {
+        return None;
+    }
+
+    let hash = url.query_pairs().find_map(|(key, value)| {
+        (key == "xt")
+            .then(|| value.strip_prefix("urn:btih:").map(str::to_owned))
+            .flatten()
+    })?;
+    match hash.len() {
+        40 if hash.bytes().all(|b| b.is_ascii_hexdigit()) => Some(hash.to_ascii_lowercase()),
+        32 => decode_base32(&hash).map(|bytes| bytes.iter().map(|b| format!("{b:02x}")).collect()),
+        _ => None,
+    }
+}
+
+/// Decode RFC 4648 base32 without padding, as used by old magnet links
+fn decode_base32(s: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(s.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u64, 0);
+    for c in s.bytes() {
+        let value = match c.to_ascii_uppercase() {
+            c @ b'A'..=b'Z' => c - b'A',
+            c @ b'2'..=b'7' => c - b'2' + 26,
+            _ => return None,
+        };
+        buffer = (buffer << 5) | value as u64;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+/// Decode a hex SHA-1 piece hash, as returned by
+/// [`Qbit::get_torrent_pieces_hashes`](crate::Qbit::get_torrent_pieces_hashes).
+/// Returns `None` unless it's exactly 40 hex characters.
+pub fn decode_piece_hash(hex: &str) -> Option<[u8; 20]> {
+    let hex = hex.as_bytes();
+    if hex.len() != 40 {
+        return None;
+    }
+
+    let mut bytes = [0; 20];
+    for (byte, pair) in bytes.iter_mut().zip(hex.chunks_exact(2)) {
+        let digit = |c: u8| (c as char).to_digit(16);
+        *byte = (digit(pair[0])? << 4 | digit(pair[1])?) as u8;
+    }
+    Some(bytes)
+}
+
 #[cfg_attr(feature = "builder", derive(typed_builder::TypedBuilder))]
 #[derive(Debug, Clone, PartialEq, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -507,6 +1358,42 @@ pub struct SetTorrentSharedLimitArg {
     pub inactive_seeding_time_limit: Option<SeedingTimeLimit>,
 }
 
+impl SetTorrentSharedLimitArg {
+    /// Whether the limits reported by `torrent` match every limit set in this
+    /// argument. Limits that are not reported by the torrent are considered
+    /// not applied.
+    pub fn is_applied_to(&self, torrent: &Torrent) -> bool {
+        fn check<T, R>(limit: Option<T>, raw: Option<R>, eq: impl Fn(T, R) -> bool) -> bool {
+            match (limit, raw) {
+                (None, _) => true,
+                (Some(limit), Some(raw)) => eq(limit, raw),
+                (Some(_), None) => false,
+            }
+        }
+
+        check(self.ratio_limit, torrent.ratio_limit, |l, r| {
+            (l.as_raw() - r).abs() < 1e-6
+        }) && check(self.seeding_time_limit, torrent.seeding_time_limit, |l, r| {
+            l.as_raw() == r
+        }) && check(
+            self.inactive_seeding_time_limit,
+            torrent.inactive_seeding_time_limit,
+            |l, r| l.as_raw() == r,
+        )
+    }
+}
+
+/// Report returned by
+/// [`Qbit::set_and_verify_share_limits`](crate::Qbit::set_and_verify_share_limits)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShareLimitsReport {
+    /// Hashes of torrents whose limits match the requested values
+    pub applied: Vec<String>,
+    /// Hashes of torrents whose limits don't match the requested values, or
+    /// that were not found when reading the limits back
+    pub not_applied: Vec<String>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub enum RatioLimit {
     Global,
@@ -514,6 +1401,17 @@ pub enum RatioLimit {
     Limited(f64),
 }
 
+impl RatioLimit {
+    /// Value used by the API to represent this limit
+    fn as_raw(self) -> f64 {
+        match self {
+            Self::Global => -2.0,
+            Self::NoLimit => -1.0,
+            Self::Limited(limit) => limit,
+        }
+    }
+}
+
 impl Serialize for RatioLimit {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -527,6 +1425,27 @@ impl Serialize for RatioLimit {
     }
 }
 
+impl<'de> Deserialize<'de> for RatioLimit {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        f64::deserialize(deserializer).map(Self::from)
+    }
+}
+
+impl From<f64> for RatioLimit {
+    fn from(raw: f64) -> Self {
+        if raw == -2.0 {
+            Self::Global
+        } else if raw == -1.0 {
+            Self::NoLimit
+        } else {
+            Self::Limited(raw)
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub enum SeedingTimeLimit {
     Global,
@@ -535,6 +1454,17 @@ pub enum SeedingTimeLimit {
     Limited(u64),
 }
 
+impl SeedingTimeLimit {
+    /// Value used by the API to represent this limit
+    fn as_raw(self) -> i64 {
+        match self {
+            Self::Global => -2,
+            Self::NoLimit => -1,
+            Self::Limited(limit) => limit as i64,
+        }
+    }
+}
+
 impl Serialize for SeedingTimeLimit {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -548,6 +1478,24 @@ impl Serialize for SeedingTimeLimit {
     }
 }
 
+impl<'de> Deserialize<'de> for SeedingTimeLimit {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match i64::deserialize(deserializer)? {
+            -2 => Ok(Self::Global),
+            -1 => Ok(Self::NoLimit),
+            raw => u64::try_from(raw).map(Self::Limited).map_err(|_| {
+                serde::de::Error::invalid_value(
+                    serde::de::Unexpected::Signed(raw),
+                    &"-2, -1 or a number of minutes",
+                )
+            }),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
 pub(crate) struct HashArg<'a> {
     hash: &'a str,
@@ -571,3 +1519,571 @@ impl HashesArg {
         }
     }
 }
+
+#[test]
+fn test_eta_duration() {
+    let torrent = |eta| Torrent {
+        eta,
+        ..Torrent::default()
+    };
+
+    assert_eq!(torrent(Some(Torrent::INFINITE_ETA)).eta_duration(), None);
+    assert_eq!(torrent(Some(-1)).eta_duration(), None);
+    assert_eq!(torrent(None).eta_duration(), None);
+    assert_eq!(
+        torrent(Some(90)).eta_duration(),
+        Some(Duration::from_secs(90))
+    );
+}
+
+#[test]
+fn test_add_torrent_arg_download_path() {
+    let arg = AddTorrentArg {
+        download_path: Some("/incomplete".to_owned()),
+        use_download_path: Some(true),
+        ..AddTorrentArg::default()
+    };
+    let value = serde_json::to_value(arg).unwrap();
+    assert_eq!(value["downloadPath"], "/incomplete");
+    assert_eq!(value["useDownloadPath"], true);
+
+    let value = serde_json::to_value(AddTorrentArg::default()).unwrap();
+    assert!(value.get("downloadPath").is_none());
+    assert!(value.get("useDownloadPath").is_none());
+}
+
+#[test]
+fn test_add_torrent_arg_limits() {
+    let arg = AddTorrentArg {
+        ratio_limit: Some(RatioLimit::Global),
+        seeding_time_limit: Some(SeedingTimeLimit::Limited(60)),
+        ..AddTorrentArg::default()
+    };
+    let value = serde_json::to_value(arg).unwrap();
+
+    assert_eq!(value["ratioLimit"], -2);
+    assert_eq!(value["seedingTimeLimit"], 60);
+}
+
+#[test]
+fn test_torrent_filter_str() {
+    for filter in TorrentFilter::VARIANTS {
+        let s = filter.to_string();
+        assert_eq!(&s.parse::<TorrentFilter>().unwrap(), filter);
+        assert_eq!(serde_json::to_string(filter).unwrap(), format!("{s:?}"));
+    }
+
+    let err = "seeding".parse::<TorrentFilter>().unwrap_err();
+    assert!(err.to_string().contains("stalledUploading"));
+}
+
+#[test]
+fn test_state_str() {
+    for state in State::VARIANTS {
+        let s = state.to_string();
+        assert_eq!(&s.parse::<State>().unwrap(), state);
+        assert_eq!(serde_json::to_string(state).unwrap(), format!("{s:?}"));
+    }
+
+    assert_eq!("stoppedUP".parse::<State>().unwrap(), State::PausedUP);
+    assert_eq!("stoppedDL".parse::<State>().unwrap(), State::PausedDL);
+    assert!("seeding".parse::<State>().is_err());
+}
+
+#[test]
+fn test_limits_roundtrip() {
+    for limit in [
+        RatioLimit::Global,
+        RatioLimit::NoLimit,
+        RatioLimit::Limited(0.0),
+        RatioLimit::Limited(1.5),
+    ] {
+        let json = serde_json::to_string(&limit).unwrap();
+        assert_eq!(serde_json::from_str::<RatioLimit>(&json).unwrap(), limit);
+    }
+    assert_eq!(
+        serde_json::from_str::<RatioLimit>("-2.0").unwrap(),
+        RatioLimit::Global
+    );
+
+    for limit in [
+        SeedingTimeLimit::Global,
+        SeedingTimeLimit::NoLimit,
+        SeedingTimeLimit::Limited(0),
+        SeedingTimeLimit::Limited(60),
+    ] {
+        let json = serde_json::to_string(&limit).unwrap();
+        assert_eq!(serde_json::from_str::<SeedingTimeLimit>(&json).unwrap(), limit);
+    }
+    assert!(serde_json::from_str::<SeedingTimeLimit>("-3").is_err());
+}
+
+#[test]
+fn test_working_tracker() {
+    let torrent = |tracker: Option<&str>| Torrent {
+        tracker: tracker.map(str::to_owned),
+        ..Torrent::default()
+    };
+
+    assert_eq!(torrent(None).working_tracker(), None);
+    assert_eq!(torrent(Some("")).working_tracker(), None);
+    assert_eq!(torrent(Some("")).primary_tracker_host(), None);
+
+    let https = torrent(Some("https://tracker.example.org/announce?passkey=abc"));
+    assert_eq!(
+        https.working_tracker(),
+        Some("https://tracker.example.org/announce?passkey=abc")
+    );
+    assert_eq!(
+        https.primary_tracker_host().as_deref(),
+        Some("tracker.example.org")
+    );
+
+    let udp = torrent(Some("udp://tracker.example.net:1337/announce"));
+    assert_eq!(
+        udp.primary_tracker_host().as_deref(),
+        Some("tracker.example.net")
+    );
+}
+
+/// Torrent with only the fields used for grouping and filtering set
+#[cfg(test)]
+fn torrent(hash: &str, category: Option<&str>, tags: Option<&str>) -> Torrent {
+    Torrent {
+        hash: Some(hash.to_owned()),
+        category: category.map(str::to_owned),
+        tags: tags.map(str::to_owned),
+        ..Torrent::default()
+    }
+}
+
+/// Half downloaded file with the given layout and availability
+#[cfg(test)]
+fn content(index: u64, name: &str, size: u64, availability: f64) -> TorrentContent {
+    TorrentContent {
+        index,
+        name: name.to_owned(),
+        size,
+        progress: 0.5,
+        priority: Priority::Normal,
+        is_seed: None,
+        piece_range: vec![],
+        availability,
+    }
+}
+
+#[test]
+fn test_group_torrents() {
+    let torrents = vec![
+        torrent("a", Some("linux"), Some("iso, seed")),
+        torrent("b", Some("linux"), Some("")),
+        torrent("c", Some(""), Some("seed")),
+        torrent("d", None, None),
+    ];
+    let hashes = |groups: &HashMap<String, Vec<Torrent>>, key: &str| {
+        let mut hashes = groups[key]
+            .iter()
+            .map(|t| t.hash.clone().unwrap())
+            .collect::<Vec<_>>();
+        hashes.sort();
+        hashes
+    };
+
+    let by_category = group_torrents_by_category(torrents.clone());
+    assert_eq!(by_category.len(), 2);
+    assert_eq!(hashes(&by_category, "linux"), ["a", "b"]);
+    assert_eq!(hashes(&by_category, ""), ["c", "d"]);
+
+    let by_tag = group_torrents_by_tag(torrents);
+    assert_eq!(by_tag.len(), 3);
+    assert_eq!(hashes(&by_tag, "iso"), ["a"]);
+    assert_eq!(hashes(&by_tag, "seed"), ["a", "c"]);
+    assert_eq!(hashes(&by_tag, ""), ["b", "d"]);
+}
+
+#[test]
+fn test_content_availability() {
+    let file = |size, availability| content(0, "", size, availability);
+
+    let contents = [file(100, 1.0), file(300, 0.5)];
+    assert!((content_availability(&contents) - 0.625).abs() < 1e-9);
+
+    let contents = [file(0, 1.0), file(200, 2.0)];
+    assert!((content_availability(&contents) - 2.0).abs() < 1e-9);
+
+    assert_eq!(content_availability(&[]), 0.0);
+    assert_eq!(content_availability(&[file(0, 1.0)]), 0.0);
+}
+
+#[test]
+fn test_build_file_tree() {
+    let file = |index, name, size| content(index, name, size, 1.0);
+    let contents = [
+        file(0, "show/season 1/e01.mkv", 100),
+        file(1, "show/season 1/e02.mkv", 200),
+        file(2, "show/season 2/e01.mkv", 300),
+        file(3, "show/extras/season 1/trailer.mkv", 10),
+        file(4, "show/readme.txt", 1),
+        file(5, "show//notes/", 2),
+    ];
+
+    let tree = build_file_tree(&contents);
+    assert_eq!(tree.name, "");
+    assert!(tree.files.is_empty());
+    assert_eq!(tree.size(), 613);
+
+    let [show] = &tree.children[..] else {
+        panic!("Expected a single root directory");
+    };
+    assert_eq!(show.name, "show");
+    assert_eq!(
+        show.children.iter().map(|d| &d.name[..]).collect::<Vec<_>>(),
+        ["season 1", "season 2", "extras"]
+    );
+    assert_eq!(
+        show.files.iter().map(|f| &f.name[..]).collect::<Vec<_>>(),
+        ["readme.txt", "notes"]
+    );
+    assert_eq!(show.files[1].index, 5);
+
+    let season_1 = &show.children[0];
+    assert_eq!(
+        season_1
+            .files
+            .iter()
+            .map(|f| (f.index, &f.name[..]))
+            .collect::<Vec<_>>(),
+        [(0, "e01.mkv"), (1, "e02.mkv")]
+    );
+    assert_eq!(season_1.size(), 300);
+
+    let extras = &show.children[2];
+    assert_eq!(extras.children[0].name, "season 1");
+    assert_eq!(extras.children[0].files[0].name, "trailer.mkv");
+}
+
+#[test]
+fn test_filter_torrents() {
+    let torrents = vec![
+        torrent("a", Some("linux"), Some("iso, seed")),
+        torrent("b", Some("bsd"), Some("iso")),
+        torrent("c", Some("games"), Some("seed")),
+        torrent("d", None, None),
+    ];
+    let filter = |categories: &[&str], tags: &[&str]| {
+        let categories = categories.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        let tags = tags.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        filter_torrents(torrents.clone(), &categories, &tags)
+            .into_iter()
+            .map(|t| t.hash.unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(filter(&[], &[]), ["a", "b", "c", "d"]);
+    assert_eq!(filter(&["linux", "bsd"], &[]), ["a", "b"]);
+    assert_eq!(filter(&["linux", "games"], &["seed"]), ["a", "c"]);
+    assert_eq!(filter(&["bsd", "games"], &["iso"]), ["b"]);
+    assert_eq!(filter(&[""], &[""]), ["d"]);
+    assert_eq!(filter(&[], &["", "iso"]), ["a", "b", "d"]);
+}
+
+#[test]
+fn test_torrent_limits() {
+    let unlimited = Torrent {
+        dl_limit: Some(-1),
+        up_limit: Some(0),
+        max_ratio: Some(-1.0),
+        ratio_limit: Some(-2.0),
+        ..Torrent::default()
+    };
+    assert_eq!(unlimited.download_limit(), None);
+    assert_eq!(unlimited.upload_limit(), None);
+    assert_eq!(unlimited.max_share_ratio(), None);
+    assert_eq!(unlimited.share_ratio_limit(), Some(RatioLimit::Global));
+
+    let limited = Torrent {
+        dl_limit: Some(1024),
+        up_limit: Some(512),
+        max_ratio: Some(1.5),
+        ratio_limit: Some(1.5),
+        ..Torrent::default()
+    };
+    assert_eq!(limited.download_limit(), Some(1024));
+    assert_eq!(limited.upload_limit(), Some(512));
+    assert_eq!(limited.max_share_ratio(), Some(1.5));
+    assert_eq!(limited.share_ratio_limit(), Some(RatioLimit::Limited(1.5)));
+
+    let absent = Torrent::default();
+    assert_eq!(absent.download_limit(), None);
+    assert_eq!(absent.share_ratio_limit(), None);
+}
+
+#[test]
+fn test_data_root() {
+    let single = Torrent {
+        save_path: Some("/downloads/".to_owned()),
+        content_path: Some("/downloads/movie.mkv".to_owned()),
+        ..Torrent::default()
+    };
+    assert_eq!(single.data_root(), Some(Path::new("/downloads/movie.mkv")));
+
+    let multi = Torrent {
+        save_path: Some("/downloads/".to_owned()),
+        content_path: Some("/downloads/Show S01".to_owned()),
+        ..Torrent::default()
+    };
+    assert_eq!(multi.data_root(), Some(Path::new("/downloads/Show S01")));
+
+    let missing = Torrent {
+        content_path: Some(String::new()),
+        ..Torrent::default()
+    };
+    assert_eq!(missing.data_root(), None);
+    assert_eq!(Torrent::default().data_root(), None);
+}
+
+#[test]
+fn test_get_torrent_list_arg_filter() {
+    // Sent as a query string, like `Qbit::get_torrent_list` does
+    let query = |arg: GetTorrentListArg| {
+        reqwest::Client::new()
+            .get("http://localhost/")
+            .query(&arg)
+            .build()
+            .unwrap()
+            .url()
+            .query()
+            .map(str::to_owned)
+    };
+
+    assert_eq!(query(GetTorrentListArg::default()), None);
+    assert_eq!(
+        query(GetTorrentListArg::everything()).as_deref(),
+        Some("filter=all")
+    );
+}
+
+#[test]
+fn test_web_seed_malformed_url() {
+    let seeds: Vec<WebSeed> = serde_json::from_value(serde_json::json!([
+        { "url": "http://seed.example/files/" },
+        { "url": "not a url" },
+        { "url": "http://[broken/" },
+    ]))
+    .unwrap();
+
+    assert_eq!(
+        seeds[0].url.as_url(),
+        Some(&Url::parse("http://seed.example/files/").unwrap())
+    );
+    assert_eq!(seeds[1].url, UrlOrRaw::Raw("not a url".to_owned()));
+    assert_eq!(seeds[2].url.as_url(), None);
+    assert_eq!(seeds[2].url.to_string(), "http://[broken/");
+}
+
+#[test]
+fn test_decode_piece_hash() {
+    // SHA-1 of an empty input
+    assert_eq!(
+        decode_piece_hash("da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+        Some([
+            0xda, 0x39, 0xa3, 0xee, 0x5e, 0x6b, 0x4b, 0x0d, 0x32, 0x55, 0xbf, 0xef, 0x95, 0x60,
+            0x18, 0x90, 0xaf, 0xd8, 0x07, 0x09,
+        ])
+    );
+    assert_eq!(
+        decode_piece_hash("DA39A3EE5E6B4B0D3255BFEF95601890AFD80709"),
+        decode_piece_hash("da39a3ee5e6b4b0d3255bfef95601890afd80709")
+    );
+    assert_eq!(decode_piece_hash("da39a3ee"), None);
+    assert_eq!(
+        decode_piece_hash("zz39a3ee5e6b4b0d3255bfef95601890afd80709"),
+        None
+    );
+    assert_eq!(
+        decode_piece_hash("+a39a3ee5e6b4b0d3255bfef95601890afd80709"),
+        None
+    );
+}
+
+#[test]
+fn test_reannounce_due() {
+    let property: TorrentProperty = serde_json::from_value(serde_json::json!({})).unwrap();
+    let property = |reannounce| TorrentProperty {
+        reannounce,
+        ..property.clone()
+    };
+    let min_interval = Duration::from_secs(60);
+
+    assert!(property(Some(0)).reannounce_due(min_interval));
+    assert!(property(Some(60)).reannounce_due(min_interval));
+    assert!(!property(Some(61)).reannounce_due(min_interval));
+    assert!(!property(Some(1800)).reannounce_due(min_interval));
+    assert!(!property(None).reannounce_due(min_interval));
+}
+
+#[test]
+fn test_magnet_info_hash() {
+    let hash = |url: &str| magnet_info_hash(&url.parse().unwrap());
+
+    assert_eq!(
+        hash("magnet:?xt=urn:btih:C12FE1C06BBA254A9DC9F519B335AA7C1367A88A&dn=test").as_deref(),
+        Some("c12fe1c06bba254a9dc9f519b335aa7c1367a88a")
+    );
+    assert_eq!(
+        hash("magnet:?dn=test&xt=urn:btih:YEX6DQDLXISUVHOJ6UM3GNNKPQJWPKEK").as_deref(),
+        Some("c12fe1c06bba254a9dc9f519b335aa7c1367a88a")
+    );
+    assert_eq!(hash("magnet:?xt=urn:btmh:1220abcd"), None);
+    assert_eq!(hash("magnet:?xt=urn:btih:xyz"), None);
+    assert_eq!(hash("https://example.com/a.torrent"), None);
+}
+
+#[test]
+fn test_priority_u8() {
+    for priority in [
+        Priority::DoNotDownload,
+        Priority::Normal,
+        Priority::Mixed,
+        Priority::High,
+        Priority::Maximal,
+    ] {
+        assert_eq!(Priority::from_u8(priority.as_u8()), Some(priority));
+    }
+    assert_eq!(Priority::from_u8(6), Some(Priority::High));
+
+    for gap in [2, 3, 5, 8, u8::MAX] {
+        assert_eq!(Priority::from_u8(gap), None);
+    }
+}
+
+#[test]
+fn test_torrent_summary() {
+    let summary: TorrentSummary = serde_json::from_value(serde_json::json!({
+        "hash": "abc",
+        "name": "ubuntu.iso",
+        "state": "uploading",
+        "progress": 1.0,
+        "tags": "iso, seed",
+        "trackers_count": 2,
+    }))
+    .unwrap();
+
+    assert_eq!(
+        summary,
+        TorrentSummary {
+            hash: Some("abc".to_owned()),
+            name: Some("ubuntu.iso".to_owned()),
+            state: Some(State::Uploading),
+            progress: Some(1.0),
+        }
+    );
+}
+
+#[test]
+fn test_swarm_health() {
+    let torrent = |availability, num_seeds, num_complete| Torrent {
+        availability: Some(availability),
+        num_seeds: Some(num_seeds),
+        num_complete: Some(num_complete),
+        ..Torrent::default()
+    };
+
+    assert!(torrent(1.0, 0, 0).is_fully_available());
+    assert!(torrent(2.5, 0, 0).is_fully_available());
+    assert!(!torrent(0.99, 0, 0).is_fully_available());
+    assert!(!torrent(-1.0, 0, 0).is_fully_available());
+    assert!(!Torrent::default().is_fully_available());
+
+    assert_eq!(torrent(3.0, 2, 10).swarm_health(), SwarmHealth::Healthy);
+    assert_eq!(torrent(1.0, 0, 1).swarm_health(), SwarmHealth::Healthy);
+    assert_eq!(torrent(1.5, 0, 0).swarm_health(), SwarmHealth::Degraded);
+    assert_eq!(torrent(0.5, 0, 3).swarm_health(), SwarmHealth::Degraded);
+    assert_eq!(torrent(0.5, 0, 0).swarm_health(), SwarmHealth::Dead);
+    assert_eq!(Torrent::default().swarm_health(), SwarmHealth::Dead);
+}
+
+#[test]
+fn test_torrent_sort_key() {
+    let torrent = serde_json::to_value(Torrent::default()).unwrap();
+    for key in TorrentSortKey::VARIANTS {
+        let json = serde_json::to_value(key).unwrap();
+        assert_eq!(json, key.as_str());
+        assert!(torrent.get(key.as_str()).is_some(), "{key} is not a field");
+    }
+    // Embedded trackers can't be sorted by
+    assert_eq!(
+        TorrentSortKey::VARIANTS.len(),
+        torrent.as_object().unwrap().len() - 1
+    );
+    assert_eq!(TorrentSortKey::FLPiecePrio.as_str(), "f_l_piece_prio");
+    assert_eq!(String::from(TorrentSortKey::Dlspeed), "dlspeed");
+
+    #[cfg(feature = "builder")]
+    {
+        let arg = GetTorrentListArg::builder()
+            .sort(TorrentSortKey::AddedOn)
+            .build();
+        assert_eq!(arg.sort.as_deref(), Some("added_on"));
+        let arg = GetTorrentListArg::builder().sort("name").build();
+        assert_eq!(arg.sort.as_deref(), Some("name"));
+    }
+}
+
+#[test]
+fn test_state_category() {
+    use StateCategory::*;
+
+    let expected = [
+        (State::Error, Errored),
+        (State::MissingFiles, Errored),
+        (State::Uploading, Seeding),
+        (State::PausedUP, Paused),
+        (State::QueuedUP, Queued),
+        (State::StalledUP, Seeding),
+        (State::CheckingUP, Checking),
+        (State::ForcedUP, Seeding),
+        (State::Allocating, Downloading),
+        (State::Downloading, Downloading),
+        (State::MetaDL, Downloading),
+        (State::PausedDL, Paused),
+        (State::QueuedDL, Queued),
+        (State::StalledDL, Downloading),
+        (State::CheckingDL, Checking),
+        (State::ForcedDL, Downloading),
+        (State::CheckingResumeData, Checking),
+        (State::Moving, Other),
+        (State::Unknown, Other),
+    ];
+    assert_eq!(expected.len(), State::VARIANTS.len());
+    for (state, category) in expected {
+        assert_eq!(state.category(), category, "{state}");
+    }
+}
+
+#[test]
+fn test_torrent_embedded_trackers() {
+    let torrents: Vec<Torrent> = serde_json::from_value(serde_json::json!([
+        {
+            "hash": "abc",
+            "trackers": [{
+                "url": "http://a.example/announce",
+                "status": 2,
+                "tier": 0,
+                "num_peers": 3,
+                "num_seeds": 1,
+                "num_leeches": 2,
+                "num_downloaded": 5,
+                "msg": "",
+            }],
+        },
+        { "hash": "def" },
+    ]))
+    .unwrap();
+
+    let trackers = torrents[0].trackers.as_ref().unwrap();
+    assert_eq!(trackers.len(), 1);
+    assert_eq!(trackers[0].url, "http://a.example/announce");
+    assert_eq!(trackers[0].num_peers, 3);
+    assert_eq!(torrents[1].trackers, None);
+}
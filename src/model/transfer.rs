@@ -1,4 +1,4 @@
-#[derive(Debug, Clone, serde::Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
 pub struct TransferInfo {
     /// Global download rate (bytes/s)
     pub dl_info_speed: u64,
@@ -16,9 +16,34 @@ pub struct TransferInfo {
     pub dht_nodes: u64,
     /// Connection status. Possible values: connected, disconnected, firewalled
     pub connection_status: ConnectionStatus,
+    /// True if torrent queueing is enabled
+    pub queueing: Option<bool>,
+    /// True if alternative speed limits are enabled
+    pub use_alt_speed_limits: Option<bool>,
+    /// Transfer list refresh interval (milliseconds)
+    pub refresh_interval: Option<u64>,
+    /// Last external IPv4 address, empty if unknown. For qBittorrent ≥ v5.0
+    pub last_external_address_v4: Option<String>,
+    /// Last external IPv6 address, empty if unknown. For qBittorrent ≥ v5.0
+    pub last_external_address_v6: Option<String>,
 }
 
-#[derive(Debug, Clone, serde::Deserialize, PartialEq, Eq)]
+/// What a dashboard shows on each refresh, see
+/// [`Qbit::get_dashboard_snapshot`](crate::Qbit::get_dashboard_snapshot)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DashboardSnapshot {
+    pub transfer: TransferInfo,
+    /// Whether alternative speed limits are enabled
+    pub alt_speed_limits: bool,
+    /// Global download limit (bytes/s), `0` if unlimited
+    pub download_limit: u64,
+    /// Global upload limit (bytes/s), `0` if unlimited
+    pub upload_limit: u64,
+    /// Number of torrents
+    pub torrent_count: usize,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub enum ConnectionStatus {
     Connected,
@@ -27,3 +52,54 @@ pub enum ConnectionStatus {
     #[serde(other)]
     Unknown,
 }
+
+#[test]
+fn test_transfer_info_roundtrip() {
+    let info = TransferInfo {
+        dl_info_speed: 1024,
+        dl_info_data: 2048,
+        up_info_speed: 512,
+        up_info_data: 4096,
+        dl_rate_limit: 0,
+        up_rate_limit: 100,
+        dht_nodes: 42,
+        connection_status: ConnectionStatus::Firewalled,
+        queueing: Some(true),
+        use_alt_speed_limits: Some(false),
+        refresh_interval: Some(1500),
+        last_external_address_v4: Some("203.0.113.7".to_owned()),
+        last_external_address_v6: None,
+    };
+    let json = serde_json::to_string(&info).unwrap();
+    assert_eq!(serde_json::from_str::<TransferInfo>(&json).unwrap(), info);
+}
+
+#[test]
+fn test_transfer_info_newer_fields() {
+    let json = serde_json::json!({
+        "connection_status": "connected",
+        "dht_nodes": 386,
+        "dl_info_data": 681521119,
+        "dl_info_speed": 0,
+        "dl_rate_limit": 0,
+        "up_info_data": 10747904,
+        "up_info_speed": 0,
+        "up_rate_limit": 1048576,
+    });
+    let old = serde_json::from_value::<TransferInfo>(json.clone()).unwrap();
+    assert_eq!(old.queueing, None);
+    assert_eq!(old.last_external_address_v4, None);
+
+    let mut json = json;
+    json["queueing"] = true.into();
+    json["use_alt_speed_limits"] = false.into();
+    json["refresh_interval"] = 1500.into();
+    json["last_external_address_v4"] = "203.0.113.7".into();
+    json["last_external_address_v6"] = "".into();
+    let new = serde_json::from_value::<TransferInfo>(json).unwrap();
+    assert_eq!(new.queueing, Some(true));
+    assert_eq!(new.use_alt_speed_limits, Some(false));
+    assert_eq!(new.refresh_interval, Some(1500));
+    assert_eq!(new.last_external_address_v4.as_deref(), Some("203.0.113.7"));
+    assert_eq!(new.last_external_address_v6.as_deref(), Some(""));
+}
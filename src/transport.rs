@@ -0,0 +1,154 @@
+//! Transport used to send requests, replaceable with
+//! [`QbitBuilder::transport`](crate::QbitBuilder::transport), e.g. to test code
+//! using [`Qbit`](crate::Qbit) without a qBittorrent instance.
+
+use std::{future::Future, pin::Pin};
+
+use reqwest::{Client, Request, Response};
+
+use crate::Result;
+
+/// Future returned by [`Transport::execute`]
+pub type TransportFuture<'a> = Pin<Box<dyn Future<Output = Result<Response>> + Send + 'a>>;
+
+/// Sends the requests built by [`Qbit`](crate::Qbit) and returns their
+/// responses. Session handling, retries and status mapping happen on top of it.
+///
+/// Implemented for [`Client`], which is used by default. A [`Response`] can be
+/// built from an [`http::Response`] with `Response::from`.
+pub trait Transport: Send + Sync {
+    fn execute(&self, request: Request) -> TransportFuture<'_>;
+}
+
+impl Transport for Client {
+    fn execute(&self, request: Request) -> TransportFuture<'_> {
+        Box::pin(async move { Ok(Client::execute(self, request).await?) })
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for std::sync::Arc<T> {
+    fn execute(&self, request: Request) -> TransportFuture<'_> {
+        (**self).execute(request)
+    }
+}
+
+#[cfg(feature = "mock")]
+pub use mock::*;
+
+#[cfg(feature = "mock")]
+mod mock {
+    use std::sync::Mutex;
+
+    use reqwest::{Request, Response, StatusCode};
+
+    use super::{Transport, TransportFuture};
+
+    /// [`Transport`] answering with canned responses, by path of the endpoint
+    /// such as `torrents/info`. Requests to other endpoints get a
+    /// `404 Not Found`, and every request is recorded.
+    ///
+    /// ```
+    /// use qbit_rs::{model::Credential, MockTransport, Qbit};
+    ///
+    /// # #[tokio::main(flavor = "current_thread")] async fn main() {
+    /// let transport = MockTransport::new()
+    ///     .respond("auth/login", 200, "Ok.")
+    ///     .respond("app/version", 200, "v4.6.0");
+    /// let api = Qbit::builder()
+    ///     .endpoint("http://localhost:8080")
+    ///     .credential(Credential::new("admin", "adminadmin"))
+    ///     .transport(transport)
+    ///     .build();
+    ///
+    /// assert_eq!(api.get_version().await.unwrap(), "v4.6.0");
+    /// # }
+    /// ```
+    #[derive(Debug, Default)]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "mock")))]
+    pub struct MockTransport {
+        responses: Vec<(String, StatusCode, String)>,
+        requests: Mutex<Vec<Request>>,
+    }
+
+    impl MockTransport {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Answer requests to `path` with `status` and `body`. The login
+        /// response also sets a session cookie.
+        pub fn respond(
+            mut self,
+            path: impl Into<String>,
+            status: u16,
+            body: impl Into<String>,
+        ) -> Self {
+            let status = StatusCode::from_u16(status).expect("Invalid status code");
+            self.responses.push((path.into(), status, body.into()));
+            self
+        }
+
+        /// URLs of the requests sent so far
+        pub fn requested_urls(&self) -> Vec<String> {
+            self.requests
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|req| req.url().to_string())
+                .collect()
+        }
+
+        fn response_for(&self, request: &Request) -> Response {
+            let url_path = request.url().path();
+            let (path, status, body) = self
+                .responses
+                .iter()
+                .find(|(path, ..)| url_path.ends_with(&format!("/{path}")))
+                .map(|(path, status, body)| (path.as_str(), *status, body.clone()))
+                .unwrap_or(("", StatusCode::NOT_FOUND, String::new()));
+
+            let mut builder = http::Response::builder().status(status);
+            if path == "auth/login" {
+                builder = builder.header(reqwest::header::SET_COOKIE, "SID=mock; HttpOnly");
+            }
+            builder.body(body).unwrap().into()
+        }
+    }
+
+    impl Transport for MockTransport {
+        fn execute(&self, request: Request) -> TransportFuture<'_> {
+            let response = self.response_for(&request);
+            self.requests.lock().unwrap().push(request);
+            Box::pin(async move { Ok(response) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mock_transport() {
+        use crate::{ApiError, Error, Qbit};
+
+        let transport = std::sync::Arc::new(
+            MockTransport::new()
+                .respond("app/version", 200, "v4.6.0")
+                .respond("torrents/properties", 404, ""),
+        );
+        let api = Qbit::builder()
+            .endpoint("http://localhost:8080")
+            .cookie("SID=test")
+            .transport(transport.clone())
+            .build();
+
+        assert_eq!(api.get_version().await.unwrap(), "v4.6.0");
+        assert!(matches!(
+            api.get_torrent_properties("abc").await,
+            Err(Error::ApiError(ApiError::TorrentNotFound))
+        ));
+        assert_eq!(
+            transport.requested_urls(),
+            [
+                "http://localhost:8080/api/v2/app/version",
+                "http://localhost:8080/api/v2/torrents/properties?hash=abc",
+            ]
+        );
+    }
+}